@@ -0,0 +1,182 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Expr, Field, Fields, LitStr, Result, Token, meta::ParseNestedMeta,
+    parse_macro_input,
+};
+
+// #[derive(Validate)] for structs with named fields, the rules are read from
+// #[validate(...)] field attributes, see single_threaded_server::validation
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input, "Validate can only be derived for structs with named fields")),
+        },
+        _ => return Err(Error::new_spanned(&input, "Validate can only be derived for structs with named fields")),
+    };
+    let mut checks = Vec::new();
+    for field in fields {
+        for rule in field_rules(field)? {
+            checks.push(rule.check(field));
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::single_threaded_server::validation::Validate for #name #type_generics #where_clause {
+            fn validate(&self) -> ::std::result::Result<(), ::single_threaded_server::validation::ValidationErrors> {
+                #[allow(unused_mut)]
+                let mut errors = ::single_threaded_server::validation::ValidationErrors::new();
+                #(#checks)*
+                errors.into_result()
+            }
+        }
+    })
+}
+
+enum RuleKind {
+    Length { min: Option<Expr>, max: Option<Expr> },
+    Range { min: Option<Expr>, max: Option<Expr> },
+    Regex(LitStr),
+    Email,
+    Nested,
+}
+
+struct Rule {
+    kind: RuleKind,
+    message: Option<LitStr>,
+}
+
+fn field_rules(field: &Field) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("validate")) {
+        attr.parse_nested_meta(|meta| {
+            rules.push(parse_rule(&meta)?);
+            Ok(())
+        })?;
+    }
+    Ok(rules)
+}
+
+fn parse_rule(meta: &ParseNestedMeta) -> Result<Rule> {
+    let mut min = None;
+    let mut max = None;
+    let mut pattern = None;
+    let mut message = None;
+    let rule_name = meta
+        .path
+        .get_ident()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    if meta.input.peek(Token![=]) {
+        // `regex = "..."` is short for `regex(pattern = "...")`
+        if rule_name != "regex" {
+            return Err(meta.error("only regex takes a value, e.g. regex = \"^[a-z]+$\""));
+        }
+        pattern = Some(meta.value()?.parse::<LitStr>()?);
+    } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+        meta.parse_nested_meta(|option| {
+            if option.path.is_ident("min") {
+                min = Some(option.value()?.parse::<Expr>()?);
+            } else if option.path.is_ident("max") {
+                max = Some(option.value()?.parse::<Expr>()?);
+            } else if option.path.is_ident("pattern") {
+                pattern = Some(option.value()?.parse::<LitStr>()?);
+            } else if option.path.is_ident("message") {
+                message = Some(option.value()?.parse::<LitStr>()?);
+            } else {
+                return Err(option.error("expected min, max, pattern or message"));
+            }
+            Ok(())
+        })?;
+    }
+    let kind = match rule_name.as_str() {
+        "length" | "range" if min.is_none() && max.is_none() => {
+            return Err(meta.error(format!("{} needs a min or a max", rule_name)));
+        }
+        "length" => RuleKind::Length { min, max },
+        "range" => RuleKind::Range { min, max },
+        "regex" => {
+            let pattern = pattern.ok_or_else(|| meta.error("regex needs a pattern"))?;
+            // a typo in the pattern fails the build instead of the first request
+            if let Err(err) = regex::Regex::new(&pattern.value()) {
+                return Err(Error::new_spanned(&pattern, format!("invalid regex: {}", err)));
+            }
+            RuleKind::Regex(pattern)
+        }
+        "email" => RuleKind::Email,
+        "nested" => RuleKind::Nested,
+        _ => return Err(meta.error("expected length, range, regex, email or nested")),
+    };
+    Ok(Rule { kind, message })
+}
+
+impl Rule {
+    fn check(&self, field: &Field) -> TokenStream2 {
+        let ident = field.ident.as_ref().expect("named fields have idents");
+        let field_name = ident.to_string();
+        let rules = quote!(::single_threaded_server::validation::rules);
+        let (rule_name, result) = match &self.kind {
+            RuleKind::Length { min, max } => {
+                let (min, max) = (optional(min), optional(max));
+                ("length", quote!(#rules::length(&self.#ident, #min, #max)))
+            }
+            RuleKind::Range { min, max } => {
+                let (min, max) = (optional(min), optional(max));
+                ("range", quote!(#rules::range(&self.#ident, #min, #max)))
+            }
+            RuleKind::Regex(pattern) => {
+                // compiled once, on the first request that reaches this rule, the derive
+                // already checked the pattern
+                let result = quote! {{
+                    static REGEX: ::std::sync::LazyLock<::single_threaded_server::validation::Regex> =
+                        ::std::sync::LazyLock::new(|| {
+                            ::single_threaded_server::validation::Regex::new(#pattern)
+                                .expect("the derive checked the pattern")
+                        });
+                    #rules::regex(&self.#ident, &REGEX)
+                }};
+                ("regex", result)
+            }
+            RuleKind::Email => ("email", quote!(#rules::email(&self.#ident))),
+            RuleKind::Nested => {
+                // nested violations keep their own rule names, prefixed with the field
+                return quote! {
+                    if let ::std::result::Result::Err(nested) =
+                        ::single_threaded_server::validation::Validate::validate(&self.#ident)
+                    {
+                        errors.merge(#field_name, nested);
+                    }
+                };
+            }
+        };
+        let message = match &self.message {
+            Some(message) => quote!(#message),
+            None => quote!(default_message),
+        };
+        quote! {
+            let result: ::std::result::Result<(), ::std::string::String> = #result;
+            #[allow(unused_variables)]
+            if let ::std::result::Result::Err(default_message) = result {
+                errors.add(#field_name, #rule_name, #message);
+            }
+        }
+    }
+}
+
+fn optional(value: &Option<Expr>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    convert::Infallible,
+    io::{self, ErrorKind, Read, Result as IoResult},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    extractor::FromRequestBody,
+    headers::HeaderMap,
+    parser::http_message_parser::{Request, find_field_line_index},
+};
+
+const MAX_CHUNK_LINE_LENGTH: usize = 4096;
+
+enum Framing {
+    Length(usize),
+    ChunkSize,
+    ChunkData(usize),
+    ChunkEnd,
+    Trailers,
+    Done,
+}
+
+struct BodyStream {
+    source: Arc<Mutex<dyn Read + Send>>,
+    // read from the source but not handed out yet, includes bytes past the end of the body
+    buffered: Vec<u8>,
+    framing: Framing,
+    body_read: usize,
+    max_body_size: usize,
+    broken: bool,
+}
+
+impl BodyStream {
+    fn fill(&mut self) -> IoResult<()> {
+        let mut buf = [0; 8192];
+        let n = self.source.lock().unwrap().read(&mut buf)?;
+        if n == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "connection closed before the body was complete",
+            ));
+        }
+        self.buffered.extend_from_slice(&buf[..n]);
+        Ok(())
+    }
+    // a line of the chunked framing without its CRLF
+    fn take_line(&mut self) -> IoResult<Vec<u8>> {
+        loop {
+            if let Some(line_end) = find_field_line_index(&self.buffered) {
+                let mut line: Vec<u8> = self.buffered.drain(..line_end).collect();
+                line.truncate(line_end - 2);
+                return Ok(line);
+            }
+            if self.buffered.len() > MAX_CHUNK_LINE_LENGTH {
+                return Err(invalid_body("chunk line is too long"));
+            }
+            self.fill()?;
+        }
+    }
+    fn take_data(&mut self, remaining: usize, buf: &mut [u8]) -> IoResult<usize> {
+        if self.buffered.is_empty() {
+            self.fill()?;
+        }
+        let n = remaining.min(buf.len()).min(self.buffered.len());
+        buf[..n].copy_from_slice(&self.buffered[..n]);
+        self.buffered.drain(..n);
+        self.body_read += n;
+        if self.body_read > self.max_body_size {
+            return Err(invalid_body("request body is too large"));
+        }
+        Ok(n)
+    }
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.framing {
+                Framing::Length(0) => self.framing = Framing::Done,
+                Framing::Length(remaining) => {
+                    let n = self.take_data(remaining, buf)?;
+                    self.framing = Framing::Length(remaining - n);
+                    return Ok(n);
+                }
+                Framing::ChunkSize => {
+                    let line = self.take_line()?;
+                    let size = std::str::from_utf8(&line)
+                        .ok()
+                        .and_then(|line| {
+                            // chunk extensions after `;` carry no meaning for us
+                            let size = line.split(';').next().unwrap_or_default().trim();
+                            usize::from_str_radix(size, 16).ok()
+                        })
+                        .ok_or_else(|| invalid_body("invalid chunk size"))?;
+                    self.framing = match size {
+                        0 => Framing::Trailers,
+                        size => Framing::ChunkData(size),
+                    };
+                }
+                Framing::ChunkData(remaining) => {
+                    let n = self.take_data(remaining, buf)?;
+                    self.framing = match remaining - n {
+                        0 => Framing::ChunkEnd,
+                        remaining => Framing::ChunkData(remaining),
+                    };
+                    return Ok(n);
+                }
+                Framing::ChunkEnd => {
+                    if !self.take_line()?.is_empty() {
+                        return Err(invalid_body("wrong transfer chunk encoding"));
+                    }
+                    self.framing = Framing::ChunkSize;
+                }
+                // trailer fields are skipped on a streamed body
+                Framing::Trailers => {
+                    if self.take_line()?.is_empty() {
+                        self.framing = Framing::Done;
+                    }
+                }
+                Framing::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+fn invalid_body(cause: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, cause)
+}
+
+// the request body read straight from the connection as the handler asks for it,
+// clones share the same position
+#[derive(Clone)]
+pub struct BodyReader {
+    stream: Arc<Mutex<BodyStream>>,
+}
+
+impl BodyReader {
+    // `buffered` holds the body bytes that were read together with the head, fails when
+    // the content length is not a number
+    pub fn new(source: Arc<Mutex<dyn Read + Send>>, buffered: Vec<u8>, headers: &HeaderMap, max_body_size: usize) -> IoResult<Self> {
+        let framing = match headers.get("content-length") {
+            Some(content_length) => Framing::Length(
+                content_length
+                    .parse()
+                    .map_err(|_| invalid_body("invalid content length"))?,
+            ),
+            None if headers.get("transfer-encoding") == Some("chunked") => Framing::ChunkSize,
+            None => Framing::Length(0),
+        };
+        Ok(Self::with_framing(source, buffered, framing, max_body_size))
+    }
+    pub fn from_bytes(body: Vec<u8>) -> Self {
+        let (framing, max_body_size) = (Framing::Length(body.len()), body.len());
+        Self::with_framing(Arc::new(Mutex::new(io::empty())), body, framing, max_body_size)
+    }
+    fn with_framing(source: Arc<Mutex<dyn Read + Send>>, buffered: Vec<u8>, framing: Framing, max_body_size: usize) -> Self {
+        Self {
+            stream: Arc::new(Mutex::new(BodyStream {
+                source,
+                buffered,
+                framing,
+                body_read: 0,
+                max_body_size,
+                broken: false,
+            })),
+        }
+    }
+    // reads whatever the handler left unread and returns the bytes that follow the body
+    pub fn finish(&self) -> IoResult<Vec<u8>> {
+        io::copy(&mut self.clone(), &mut io::sink())?;
+        Ok(std::mem::take(&mut self.stream.lock().unwrap().buffered))
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let mut stream = self.stream.lock().unwrap();
+        // after a failed read the framing can't be trusted anymore
+        if stream.broken {
+            return Err(invalid_body("request body could not be read"));
+        }
+        let read = stream.read(buf);
+        stream.broken = read.is_err();
+        read
+    }
+}
+
+impl FromRequestBody for BodyReader {
+    const STREAMS_BODY: bool = true;
+    type Error = Infallible;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        match request.extensions().get::<BodyReader>() {
+            Some(reader) => Ok(BodyReader::clone(&reader)),
+            // the body was already buffered, e.g. for a request built by hand
+            None => Ok(BodyReader::from_bytes(request.body().to_vec())),
+        }
+    }
+}
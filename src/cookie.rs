@@ -0,0 +1,302 @@
+use std::{
+    convert::Infallible,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    extractor::FromRequest,
+    headers::{self, TypedHeader},
+    parser::http_message_parser::Request,
+    response::{IntoResponse, Response},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+    }
+    // the Set-Cookie field value, anything that could end the pair or an attribute early
+    // is percent-encoded so a value can never smuggle in attributes or header lines
+    pub fn encode(&self) -> String {
+        let mut encoded = format!(
+            "{}={}",
+            percent_encode(&self.name, is_token),
+            percent_encode(&self.value, is_cookie_octet)
+        );
+        if let Some(path) = &self.path {
+            encoded.push_str(&format!("; Path={}", percent_encode(path, is_attribute_octet)));
+        }
+        if let Some(domain) = &self.domain {
+            encoded.push_str(&format!("; Domain={}", percent_encode(domain, is_attribute_octet)));
+        }
+        if let Some(max_age) = self.max_age {
+            encoded.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if let Some(expires) = self.expires {
+            encoded.push_str(&format!("; Expires={}", http_date(expires)));
+        }
+        if self.secure {
+            encoded.push_str("; Secure");
+        }
+        if self.http_only {
+            encoded.push_str("; HttpOnly");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => encoded.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => encoded.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => encoded.push_str("; SameSite=None"),
+            None => {}
+        }
+        encoded
+    }
+}
+
+// cookie-octet from RFC 6265 without `%`, which starts an escape
+fn is_cookie_octet(byte: u8) -> bool {
+    matches!(byte, 0x21 | 0x23..=0x24 | 0x26..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+// token from RFC 9110 without `%`
+fn is_token(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$&'*+-.^_`|~".contains(&byte)
+}
+
+// av-octet from RFC 6265, any visible character or space except `;`
+fn is_attribute_octet(byte: u8) -> bool {
+    matches!(byte, 0x20..=0x7E) && byte != b';' && byte != b'%'
+}
+
+fn percent_encode(text: &str, allowed: fn(u8) -> bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if allowed(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// escapes that don't form a valid byte are kept as they were sent
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl From<&str> for Cookie {
+    fn from(name: &str) -> Self {
+        Cookie::new(name, "")
+    }
+}
+
+impl From<String> for Cookie {
+    fn from(name: String) -> Self {
+        Cookie::new(name, "")
+    }
+}
+
+// the cookies the client sent plus the changes a handler wants sent back
+#[derive(Clone, Debug, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn from_request_headers(request: &Request) -> Self {
+        match TypedHeader::<headers::Cookie>::from_request(request) {
+            Ok(TypedHeader(cookie_header)) => CookieJar::from(cookie_header),
+            Err(_) => CookieJar::new(),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        match self.delta.iter().rev().find(|cookie| cookie.name == name) {
+            Some(cookie) if is_removal(cookie) => None,
+            Some(cookie) => Some(cookie),
+            None => self.cookies.iter().find(|cookie| cookie.name == name),
+        }
+    }
+    pub fn add(&mut self, cookie: Cookie) {
+        self.delta.retain(|existing| existing.name != cookie.name);
+        self.delta.push(cookie);
+    }
+    // path and domain must match the ones the cookie was set with for the browser to drop it
+    pub fn remove(&mut self, cookie: impl Into<Cookie>) {
+        let mut cookie = cookie.into();
+        cookie.value.clear();
+        cookie.max_age = Some(Duration::ZERO);
+        cookie.expires = Some(UNIX_EPOCH);
+        self.delta.retain(|existing| existing.name != cookie.name);
+        self.delta.push(cookie);
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        let changed = self.delta.iter().filter(|cookie| !is_removal(cookie));
+        let untouched = self
+            .cookies
+            .iter()
+            .filter(|cookie| !self.delta.iter().any(|changed| changed.name == cookie.name));
+        untouched.chain(changed)
+    }
+    pub fn delta(&self) -> &[Cookie] {
+        &self.delta
+    }
+}
+
+// the pairs of a Cookie request header, names and values percent-decoded like encode wrote them
+impl From<headers::Cookie> for CookieJar {
+    fn from(cookie_header: headers::Cookie) -> Self {
+        let cookies = cookie_header
+            .0
+            .iter()
+            .map(|(name, value)| Cookie::new(percent_decode(name), percent_decode(value)))
+            .collect();
+        Self {
+            cookies,
+            delta: Vec::new(),
+        }
+    }
+}
+
+fn is_removal(cookie: &Cookie) -> bool {
+    cookie.max_age == Some(Duration::ZERO)
+}
+
+impl FromRequest for CookieJar {
+    type Error = Infallible;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        Ok(CookieJar::from_request_headers(request))
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (CookieJar, R) {
+    fn into_response(self) -> Response {
+        let (jar, response) = self;
+        let mut response = response.into_response();
+        for cookie in jar.delta() {
+            response.append_header("Set-Cookie", &cookie.encode());
+        }
+        response
+    }
+}
+
+// IMF-fixdate, e.g. Sun, 06 Nov 1994 08:49:37 GMT
+pub fn http_date(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = seconds / 86400;
+    let seconds_of_day = seconds % 86400;
+    // civil date from days since the epoch, Howard Hinnant's days_from_civil inverted
+    let shifted = days as i64 + 719468;
+    let era = shifted / 146097;
+    let day_of_era = shifted - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
@@ -1,9 +1,12 @@
 
 
-use serde::de::DeserializeOwned;
+use std::{any::type_name, convert::Infallible, ops::Deref, sync::Arc};
+
+use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
 
 use crate::{
+    media_type::{MediaType, decode_form},
     parser::http_message_parser::Request,
     response::
         IntoResponse
@@ -19,7 +22,14 @@ pub trait FromRequest {
         Self: std::marker::Sized;
 }
 
-pub trait FromRequestBody {
+// markers that keep the blanket impl for FromRequest types apart from the body extractors,
+// which lets Option and Result wrap either kind
+pub struct ViaRequest;
+pub struct ViaBody;
+
+pub trait FromRequestBody<M = ViaBody> {
+    // true when the extractor reads the body off the connection itself, see BodyReader
+    const STREAMS_BODY: bool = false;
     type Error: IntoResponse;
     fn from_request_body(request: &Request) -> Result<Self, Self::Error>
     where
@@ -32,15 +42,15 @@ where
 {
     type Error = BodyContentError;
     fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
-        match request.header("content-type") {
-            Some(header) => {
-                if header != "application/json" {
-                    return Err(BodyContentError::ContentTypeMisMatch);
-                }
-            }
-            None => return Err(BodyContentError::ContentTypeMisMatch),
-        };
-        let result: T = serde_json::from_slice(request.body())?;
+        let media_type = body_media_type(request)?;
+        if !media_type.is_json() {
+            return Err(BodyContentError::ContentTypeMisMatch);
+        }
+        // json is always utf-8, a charset parameter may only confirm that
+        if media_type.encoding() != Some(encoding_rs::UTF_8) {
+            return Err(BodyContentError::UnsupportedCharset(media_type.charset().unwrap_or_default().to_string()));
+        }
+        let result: T = deserialize_json(request.body()).map_err(BodyContentError::JsonSerializationError)?;
         Ok(Json(result))
     }
 }
@@ -51,27 +61,156 @@ where
 {
     type Error = BodyContentError;
     fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
-        match request.header("content-type") {
-            Some(header) => {
-                if header != "application/x-www-form-urlencoded" {
-                    println!("header is {}",header);
-                    return Err(BodyContentError::ContentTypeMisMatch);
-                }
-            }
-            None => return Err(BodyContentError::ContentTypeMisMatch),
-        };
-        let result: T = serde_urlencoded::from_bytes(request.body())?;
+        let media_type = body_media_type(request)?;
+        if !media_type.is_form() {
+            return Err(BodyContentError::ContentTypeMisMatch);
+        }
+        let encoding = media_type
+            .encoding()
+            .ok_or_else(|| BodyContentError::UnsupportedCharset(media_type.charset().unwrap_or_default().to_string()))?;
+        if encoding == encoding_rs::UTF_8 {
+            let result: T = deserialize_urlencoded(request.body()).map_err(BodyContentError::UrlEncodedFormSerialization)?;
+            return Ok(Form(result));
+        }
+        // re-encoded as utf-8 so serde_urlencoded can still do the typed parsing
+        let pairs = decode_form(request.body(), encoding);
+        let utf8_form = serde_urlencoded::to_string(pairs).expect("string pairs always serialize");
+        let result: T = deserialize_urlencoded(utf8_form.as_bytes()).map_err(BodyContentError::UrlEncodedFormSerialization)?;
         Ok(Form(result))
     }
 }
+
+impl FromRequestBody for Vec<u8> {
+    type Error = Infallible;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        Ok(request.body().to_vec())
+    }
+}
+
+// the raw body whatever its content type
+pub struct Bytes(pub Vec<u8>);
+
+impl FromRequestBody for Bytes {
+    type Error = Infallible;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        Ok(Bytes(request.body().to_vec()))
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequestBody for String {
+    type Error = BodyContentError;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        String::from_utf8(request.body().to_vec()).map_err(|_| BodyContentError::InvalidUtf8)
+    }
+}
+
+fn body_media_type(request: &Request) -> Result<MediaType, BodyContentError> {
+    request
+        .header("content-type")
+        .and_then(MediaType::parse)
+        .ok_or(BodyContentError::ContentTypeMisMatch)
+}
+
 #[derive(Error, Debug)]
 pub enum BodyContentError {
     #[error("content type header mismatch")]
     ContentTypeMisMatch,
-    #[error("json serialization error: {0}")]
-    JsonSerializationError(#[from] serde_json::Error),
-    #[error("route handler not found {0}")]
-    UrlEncodedFormSerialization(#[from] serde_urlencoded::de::Error),
+    #[error("unsupported charset {0}")]
+    UnsupportedCharset(String),
+    #[error("body is not valid utf-8")]
+    InvalidUtf8,
+    #[error("invalid json body: {0}")]
+    JsonSerializationError(DeserializeError),
+    #[error("invalid form body: {0}")]
+    UrlEncodedFormSerialization(DeserializeError),
+}
+
+// where and why a request part failed to deserialize
+#[derive(Error, Debug, Serialize)]
+#[error("{message}")]
+pub struct DeserializeError {
+    #[serde(skip)]
+    pub message: String,
+    // dotted path of the failing field, None when the whole value was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    // only known for json bodies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl DeserializeError {
+    fn new(path: Option<&serde_path_to_error::Path>, message: String) -> Self {
+        let mut field = path
+            .map(ToString::to_string)
+            .filter(|path| path != ".");
+        // serde reports a missing field on its parent, the name is only in the message
+        if let Some(missing) = message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split_once('`'))
+            .map(|(name, _)| name)
+        {
+            field = Some(match field {
+                Some(parent) => format!("{}.{}", parent, missing),
+                None => missing.to_string(),
+            });
+        }
+        let expected = message
+            .rsplit_once(", expected ")
+            .map(|(_, expected)| expected.to_string());
+        Self {
+            message,
+            field,
+            expected,
+            line: None,
+            column: None,
+        }
+    }
+    fn from_json(path: Option<&serde_path_to_error::Path>, err: serde_json::Error) -> Self {
+        let message = err.to_string();
+        // serde_json appends the position, it has its own members here
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) if err.line() > 0 => message.to_string(),
+            _ => message,
+        };
+        let mut deserialize_error = Self::new(path, message);
+        if err.line() > 0 {
+            deserialize_error.line = Some(err.line());
+            deserialize_error.column = Some(err.column());
+        }
+        deserialize_error
+    }
+}
+
+pub fn deserialize_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, DeserializeError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+        let path = err.path().clone();
+        DeserializeError::from_json(Some(&path), err.into_inner())
+    })?;
+    deserializer
+        .end()
+        .map_err(|err| DeserializeError::from_json(None, err))?;
+    Ok(value)
+}
+
+pub fn deserialize_urlencoded<T: DeserializeOwned>(input: &[u8]) -> Result<T, DeserializeError> {
+    let deserializer = serde_urlencoded::Deserializer::new(form_urlencoded::parse(input));
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err.path().clone();
+        DeserializeError::new(Some(&path), err.into_inner().to_string())
+    })
 }
 
 pub struct Query<T>(pub T);
@@ -80,13 +219,17 @@ impl<T> FromRequest for Query<T>
 where
     T: DeserializeOwned,
 {
-    type Error = serde_urlencoded::de::Error;
+    type Error = QueryError;
     fn from_request(request: &Request) -> Result<Self, Self::Error> {
-        let result: T = serde_urlencoded::from_str(request.query_params_string())?;
+        let result: T = deserialize_urlencoded(request.query_params_string().as_bytes()).map_err(QueryError)?;
         Ok(Query(result))
     }
 }
 
+#[derive(Error, Debug)]
+#[error("invalid query string: {0}")]
+pub struct QueryError(pub DeserializeError);
+
 pub struct Path<T>(pub T);
 
 impl<T> FromRequest for Path<T>
@@ -101,9 +244,9 @@ where
     where
         Self: Sized,
     {
-        match request.routing().get_method_router(&request.request_method()) {
-            Some(router) => {
-                let matched_route = router.at(request.request_path())?;
+        let routing = request.routing();
+        match routing.find_route(&request.request_method(), request.request_path()) {
+            Some(matched_route) => {
                 let params = matched_route.params;
                 let query_string = params
                     .iter()
@@ -111,7 +254,8 @@ where
                     .collect::<Vec<String>>()
                     .join("&");
                 println!("query string {}", query_string);
-                let extracted_params: T = serde_urlencoded::from_str(&query_string)?;
+                let extracted_params: T =
+                    deserialize_urlencoded(query_string.as_bytes()).map_err(RoutingError::InvalidParams)?;
                 Ok(Path(extracted_params))
             }
             None => Err(RoutingError::NotFound),
@@ -119,7 +263,7 @@ where
     }
 }
 
-impl<T> FromRequestBody for T
+impl<T> FromRequestBody<ViaRequest> for T
 where T:FromRequest{
     type Error=<Self as FromRequest>::Error;
 
@@ -130,6 +274,50 @@ where T:FromRequest{
     }
 }
 
+// None instead of the rejection when the extractor fails
+impl<T> FromRequest for Option<T>
+where
+    T: FromRequest,
+{
+    type Error = Infallible;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        Ok(T::from_request(request).ok())
+    }
+}
+
+impl<T> FromRequestBody for Option<T>
+where
+    T: FromRequestBody,
+{
+    const STREAMS_BODY: bool = T::STREAMS_BODY;
+    type Error = Infallible;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        Ok(T::from_request_body(request).ok())
+    }
+}
+
+// hands the rejection to the handler instead of answering with it
+impl<T> FromRequest for Result<T, T::Error>
+where
+    T: FromRequest,
+{
+    type Error = Infallible;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        Ok(T::from_request(request))
+    }
+}
+
+impl<T> FromRequestBody for Result<T, T::Error>
+where
+    T: FromRequestBody,
+{
+    const STREAMS_BODY: bool = T::STREAMS_BODY;
+    type Error = Infallible;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        Ok(T::from_request_body(request))
+    }
+}
+
 
 #[derive(Error, Debug)]
 pub enum RoutingError {
@@ -137,7 +325,46 @@ pub enum RoutingError {
     NotFound,
     #[error("path not found {0}")]
     MatchItError(#[from] matchit::MatchError),
-    #[error("failed to deserialize multiple params {0}")]
-    SerdeUrlEncodingError(#[from] serde_urlencoded::de::Error),
+    #[error("invalid path parameters: {0}")]
+    InvalidParams(DeserializeError),
+}
+
+
+pub struct State<T>(pub Arc<T>);
+
+impl<T> FromRequest for State<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Error = StateError;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        match request.routing().state::<T>() {
+            Some(state) => Ok(State(state)),
+            None => Err(StateError::Missing(type_name::<T>())),
+        }
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
 }
 
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        State(Arc::clone(&self.0))
+    }
+}
+
+// lets a part of the application state be extracted on its own, see Server::with_sub_state
+pub trait FromRef<S> {
+    fn from_ref(input: &S) -> Self;
+}
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("no state of type {0} was registered on the server")]
+    Missing(&'static str),
+}
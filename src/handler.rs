@@ -7,6 +7,7 @@ use crate::{
 };
 
 pub trait HandlerFunction<Args>: Send + Sync + 'static + Clone + Sized {
+    const STREAMS_BODY: bool = false;
     fn execute(&self, request: Request) -> Response;
 }
 // impl<F, I, T1, T2, T3> HandlerFunction<(T1, T2, T3, I)> for F
@@ -34,7 +35,9 @@ pub trait HandlerFunction<Args>: Send + Sync + 'static + Clone + Sized {
 //     }
 // }
 
-impl<F, I> HandlerFunction<I> for F
+// keyed on the fn signature rather than the bare return type, so a return type that is
+// also an extractor (CookieJar) can't overlap the tuple impls below
+impl<F, I> HandlerFunction<fn() -> I> for F
 where
     I: IntoResponse,
     F: Fn() -> I + Send + Sync + 'static + Clone,
@@ -47,6 +50,10 @@ where
 pub trait Service: Send + Sync + 'static {
     fn call(&self, request: Request) -> Response;
     fn clone_box(&self) -> Box<dyn Service>;
+    // the connection hands the body over unparsed, the handler reads it through a BodyReader
+    fn streams_body(&self) -> bool {
+        false
+    }
 }
 
 pub struct Handler<F, Args>
@@ -93,6 +100,15 @@ where
     fn clone_box(&self) -> Box<dyn Service> {
         Box::new(self.clone())
     }
+
+    fn streams_body(&self) -> bool {
+        F::STREAMS_BODY
+    }
+}
+
+// rejections go through the server wide renderer when one is set
+fn reject<E: IntoResponse>(request: &Request, err: E) -> Response {
+    request.routing().render_rejection(err.into_response())
 }
 
 macro_rules! impl_handler {
@@ -101,24 +117,26 @@ macro_rules! impl_handler {
         [$($ty:ident),*], $last:ident
     ) => {
         #[allow(non_snake_case, unused_mut)]
-        impl<F, I, $($ty,)* $last> HandlerFunction<($($ty,)* $last,)> for F
+        impl<F, I, M, $($ty,)* $last> HandlerFunction<(M, $($ty,)* $last,)> for F
         where
             F: Fn($($ty,)* $last,) -> I + Send + Sync + 'static + Clone,
             I: IntoResponse,
             $( $ty: FromRequest, )*
-            $last: FromRequestBody,
+            $last: FromRequestBody<M>,
         {
+            const STREAMS_BODY: bool = <$last as FromRequestBody<M>>::STREAMS_BODY;
+
             fn execute(&self, request: Request) -> Response {
                 $(
                     let $ty = match $ty::from_request(&request) {
                         Ok(val) => val,
-                        Err(err) => return err.into_response(),
+                        Err(err) => return reject(&request, err),
                     };
                 )*
 
-                let $last = match $last::from_request_body(&request) {
+                let $last = match <$last as FromRequestBody<M>>::from_request_body(&request) {
                     Ok(val) => val,
-                    Err(err) => return err.into_response(),
+                    Err(err) => return reject(&request, err),
                 };
 
                 self($($ty,)* $last,).into_response()
@@ -0,0 +1,406 @@
+use std::{collections::HashMap, convert::Infallible};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use thiserror::Error;
+
+use crate::{extractor::FromRequest, parser::http_message_parser::Request};
+
+// field lines in the order they were added, names compare case-insensitively and
+// a name may appear more than once
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+    // replaces every existing value of the header
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.remove(&name);
+        self.entries.push((name, value.into()));
+    }
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((name.into(), value.into()));
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn typed_get<H: Header>(&self) -> Option<H> {
+        let values: Vec<&str> = self.get_all(H::NAME).collect();
+        if values.is_empty() {
+            return None;
+        }
+        H::decode(&values)
+    }
+    pub fn typed_insert<H: Header>(&mut self, header: H) {
+        self.insert(H::NAME, header.encode());
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            entries: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> From<HashMap<K, V>> for HeaderMap {
+    fn from(headers: HashMap<K, V>) -> Self {
+        headers.into_iter().collect()
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+pub struct Headers(pub HeaderMap);
+
+impl FromRequest for Headers {
+    type Error = Infallible;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        Ok(Headers(request.headers().clone()))
+    }
+}
+
+pub trait Header: Sized {
+    const NAME: &'static str;
+    // every value the header was sent with, in order
+    fn decode(values: &[&str]) -> Option<Self>;
+    fn encode(&self) -> String;
+}
+
+pub struct TypedHeader<T>(pub T);
+
+impl<T: Header> FromRequest for TypedHeader<T> {
+    type Error = TypedHeaderError;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        if !request.headers().contains_key(T::NAME) {
+            return Err(TypedHeaderError::Missing(T::NAME));
+        }
+        match request.headers().typed_get::<T>() {
+            Some(header) => Ok(TypedHeader(header)),
+            None => Err(TypedHeaderError::Invalid(T::NAME)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TypedHeaderError {
+    #[error("missing {0} header")]
+    Missing(&'static str),
+    #[error("invalid {0} header")]
+    Invalid(&'static str),
+}
+
+fn single_value<'a>(values: &[&'a str]) -> Option<&'a str> {
+    match values {
+        [value] => Some(value.trim()),
+        _ => None,
+    }
+}
+
+// list headers may be sent as several field lines or as one comma separated line
+fn list_items<'a>(values: &[&'a str]) -> impl Iterator<Item = &'a str> {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentType(pub String);
+
+impl ContentType {
+    // the media type without parameters, lowercased
+    pub fn essence(&self) -> String {
+        self.0
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    }
+}
+
+impl Header for ContentType {
+    const NAME: &'static str = "Content-Type";
+    fn decode(values: &[&str]) -> Option<Self> {
+        let value = single_value(values)?;
+        if !value.contains('/') {
+            return None;
+        }
+        Some(ContentType(value.to_string()))
+    }
+    fn encode(&self) -> String {
+        self.0.clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Authorization {
+    Basic { username: String, password: String },
+    Bearer(String),
+    Other { scheme: String, credentials: String },
+}
+
+impl Header for Authorization {
+    const NAME: &'static str = "Authorization";
+    fn decode(values: &[&str]) -> Option<Self> {
+        let value = single_value(values)?;
+        let (scheme, credentials) = value.split_once(' ')?;
+        let credentials = credentials.trim();
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = String::from_utf8(STANDARD.decode(credentials).ok()?).ok()?;
+            let (username, password) = decoded.split_once(':')?;
+            Some(Authorization::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            })
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            Some(Authorization::Bearer(credentials.to_string()))
+        } else {
+            Some(Authorization::Other {
+                scheme: scheme.to_string(),
+                credentials: credentials.to_string(),
+            })
+        }
+    }
+    fn encode(&self) -> String {
+        match self {
+            Authorization::Basic { username, password } => {
+                format!("Basic {}", STANDARD.encode(format!("{}:{}", username, password)))
+            }
+            Authorization::Bearer(token) => format!("Bearer {}", token),
+            Authorization::Other {
+                scheme,
+                credentials,
+            } => format!("{} {}", scheme, credentials),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaRange {
+    pub media_type: String,
+    pub quality: f32,
+}
+
+// media ranges ordered from the most to the least preferred
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accept(pub Vec<MediaRange>);
+
+impl Accept {
+    pub fn accepts(&self, media_type: &str) -> bool {
+        let (kind, _) = media_type.split_once('/').unwrap_or((media_type, ""));
+        self.0.iter().any(|range| {
+            range.quality > 0.0
+                && (range.media_type == "*/*"
+                    || range.media_type.eq_ignore_ascii_case(media_type)
+                    || range
+                        .media_type
+                        .strip_suffix("/*")
+                        .is_some_and(|range_kind| range_kind.eq_ignore_ascii_case(kind)))
+        })
+    }
+}
+
+impl Header for Accept {
+    const NAME: &'static str = "Accept";
+    fn decode(values: &[&str]) -> Option<Self> {
+        let mut media_ranges = Vec::new();
+        for item in list_items(values) {
+            let mut parts = item.split(';').map(str::trim);
+            let media_type = parts.next()?.to_lowercase();
+            if !media_type.contains('/') {
+                return None;
+            }
+            let mut quality = 1.0;
+            for parameter in parts {
+                if let Some((name, value)) = parameter.split_once('=')
+                    && name.trim().eq_ignore_ascii_case("q")
+                {
+                    quality = value.trim().parse().ok()?;
+                    if !(0.0..=1.0).contains(&quality) {
+                        return None;
+                    }
+                }
+            }
+            media_ranges.push(MediaRange {
+                media_type,
+                quality,
+            });
+        }
+        media_ranges.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+        Some(Accept(media_ranges))
+    }
+    fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|range| match range.quality {
+                1.0 => range.media_type.clone(),
+                quality => format!("{};q={}", range.media_type, quality),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserAgent(pub String);
+
+impl Header for UserAgent {
+    const NAME: &'static str = "User-Agent";
+    fn decode(values: &[&str]) -> Option<Self> {
+        Some(UserAgent(single_value(values)?.to_string()))
+    }
+    fn encode(&self) -> String {
+        self.0.clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Host {
+    pub hostname: String,
+    pub port: Option<u16>,
+}
+
+impl Header for Host {
+    const NAME: &'static str = "Host";
+    fn decode(values: &[&str]) -> Option<Self> {
+        let value = single_value(values)?;
+        // the port is whatever follows the last colon that is not inside an IPv6 literal
+        let (hostname, port) = match value.rsplit_once(':') {
+            Some((hostname, port)) if !port.contains(']') => (hostname, Some(port.parse().ok()?)),
+            _ => (value, None),
+        };
+        if hostname.is_empty() {
+            return None;
+        }
+        Some(Host {
+            hostname: hostname.to_string(),
+            port,
+        })
+    }
+    fn encode(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}", self.hostname, port),
+            None => self.hostname.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie(pub Vec<(String, String)>);
+
+impl Cookie {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Header for Cookie {
+    const NAME: &'static str = "Cookie";
+    // pairs without a `=` are skipped like browsers do, one stray cookie set by another
+    // application on the domain must not hide all the others
+    fn decode(values: &[&str]) -> Option<Self> {
+        let pairs = values
+            .iter()
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                Some((name.trim().to_string(), value.trim().trim_matches('"').to_string()))
+            })
+            .collect();
+        Some(Cookie(pairs))
+    }
+    fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl IfNoneMatch {
+    // weak comparison, as If-None-Match requires
+    pub fn matches(&self, etag: &str) -> bool {
+        let opaque = |tag: &str| tag.trim_start_matches("W/").to_string();
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|tag| opaque(tag) == opaque(etag)),
+        }
+    }
+}
+
+impl Header for IfNoneMatch {
+    const NAME: &'static str = "If-None-Match";
+    fn decode(values: &[&str]) -> Option<Self> {
+        let tags: Vec<String> = list_items(values).map(str::to_string).collect();
+        if tags.iter().any(|tag| tag == "*") {
+            return Some(IfNoneMatch::Any);
+        }
+        let quoted = |tag: &str| {
+            let tag = tag.strip_prefix("W/").unwrap_or(tag);
+            tag.len() >= 2 && tag.starts_with('"') && tag.ends_with('"')
+        };
+        if tags.is_empty() || !tags.iter().all(|tag| quoted(tag)) {
+            return None;
+        }
+        Some(IfNoneMatch::Tags(tags))
+    }
+    fn encode(&self) -> String {
+        match self {
+            IfNoneMatch::Any => "*".to_string(),
+            IfNoneMatch::Tags(tags) => tags.join(", "),
+        }
+    }
+}
@@ -3,9 +3,24 @@ pub mod server;
 pub mod response_writer;
 pub mod task_manager;
 pub mod headers_parser;
+pub mod headers;
+pub mod cookie;
+pub mod secure_cookie;
+pub mod session;
+pub mod multipart;
+pub mod body_reader;
+pub mod media_type;
+pub mod problem;
+pub mod validation;
 pub mod parser;
 pub mod proxy;
 pub mod extractor;
+pub mod extensions;
 pub mod routing;
+pub mod router;
 pub mod response;
-pub mod handler;
\ No newline at end of file
+pub mod handler;
+pub mod stream;
+pub mod listener;
+#[cfg(feature = "tls")]
+pub mod tls;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::{
+    fs,
+    io::{self, ErrorKind, Result as IoResult},
+    net::{SocketAddr, TcpListener},
+};
+
+#[cfg(unix)]
+use std::{
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        net::UnixListener,
+    },
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+#[cfg(feature = "tls")]
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use socket2::{Domain, Socket, Type};
+
+use crate::stream::HttpStream;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(feature = "tls")]
+    Tls { listener: TcpListener, server_config: Arc<ServerConfig> },
+    #[cfg(unix)]
+    Unix { listener: UnixListener, path: PathBuf },
+}
+
+impl Listener {
+    pub fn bind_tcp(address: &SocketAddr) -> IoResult<Self> {
+        Ok(Listener::Tcp(bind_tcp_listener(address)?))
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn bind_tls(address: &SocketAddr, server_config: Arc<ServerConfig>) -> IoResult<Self> {
+        Ok(Listener::Tls {
+            listener: bind_tcp_listener(address)?,
+            server_config,
+        })
+    }
+
+    #[cfg(unix)]
+    pub fn bind_unix(path: &Path, permissions: Option<u32>) -> IoResult<Self> {
+        // a socket file left behind by a previous run would make the bind fail
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(_) => {}
+        }
+        let listener = UnixListener::bind(path)?;
+        if let Some(mode) = permissions {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        listener.set_nonblocking(true)?;
+        Ok(Listener::Unix {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn accept(&self) -> IoResult<Box<dyn HttpStream>> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+            // the handshake itself happens on the first read, inside the worker thread
+            #[cfg(feature = "tls")]
+            Listener::Tls { listener, server_config } => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                let connection =
+                    ServerConnection::new(Arc::clone(server_config)).map_err(io::Error::other)?;
+                Ok(Box::new(StreamOwned::new(connection, stream)))
+            }
+            #[cfg(unix)]
+            Listener::Unix { listener, .. } => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            #[cfg(feature = "tls")]
+            Listener::Tls { listener, .. } => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix { .. } => None,
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix { path, .. } = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn bind_tcp_listener(address: &SocketAddr) -> IoResult<TcpListener> {
+    let socket = Socket::new(Domain::for_address(*address), Type::STREAM, None)?;
+    // keeps `[::]` from claiming the IPv4 port too so it can sit next to `0.0.0.0`
+    if address.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.bind(&(*address).into())?;
+    socket.listen(128)?;
+    let listener: TcpListener = socket.into();
+    // polling lets the accept loop notice a shutdown request between connections
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
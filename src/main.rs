@@ -16,8 +16,8 @@ fn main() -> IoResult<()> {
     server.post("/", root_post).unwrap();
     server.get("/favicon.ico", favicon).unwrap();
     server.get("/test/{id}/{name}", test_handler).unwrap();
-    server.listen();
-    Ok(())
+    server.shutdown_on_signals()?;
+    server.listen()
 }
 
 fn root_post() -> Json<User> {
@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+
+use encoding_rs::Encoding;
+
+// a parsed Content-Type value such as `application/vnd.api+json; charset=utf-8`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaType {
+    kind: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (essence, parameters) = value.split_once(';').unwrap_or((value, ""));
+        let (kind, subtype) = essence.trim().split_once('/')?;
+        let (kind, subtype) = (kind.trim(), subtype.trim());
+        if kind.is_empty() || subtype.is_empty() || kind.contains(char::is_whitespace) || subtype.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            kind: kind.to_lowercase(),
+            subtype: subtype.to_lowercase(),
+            parameters: parse_parameters(parameters),
+        })
+    }
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+    // the structured syntax suffix, `json` for `application/problem+json`
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype.rsplit_once('+').map(|(_, suffix)| suffix)
+    }
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.kind, self.subtype)
+    }
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+    pub fn is(&self, kind: &str, subtype: &str) -> bool {
+        self.kind == kind && self.subtype == subtype
+    }
+    pub fn is_json(&self) -> bool {
+        self.kind == "application" && (self.subtype == "json" || self.suffix() == Some("json"))
+    }
+    pub fn is_form(&self) -> bool {
+        self.is("application", "x-www-form-urlencoded")
+    }
+    // the declared charset, None when it is not one encoding_rs knows
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        match self.charset() {
+            Some(charset) => Encoding::for_label(charset.as_bytes()),
+            None => Some(encoding_rs::UTF_8),
+        }
+    }
+}
+
+// `; key=value; key="quoted; value"` pairs, quotes removed and escapes resolved
+pub fn parse_parameters(input: &str) -> Vec<(String, String)> {
+    let mut parameters = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while matches!(chars.peek(), Some(';' | ' ' | '\t')) {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ';' {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() && chars.peek().is_none() {
+            return parameters;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            }
+            while let Some(&c) = chars.peek() {
+                if c == ';' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        parameters.push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+}
+
+// urlencoded pairs whose percent-decoded bytes are in `encoding`, returned as UTF-8
+pub fn decode_form(body: &[u8], encoding: &'static Encoding) -> Vec<(String, String)> {
+    let decode = |part: &[u8]| -> String {
+        let bytes = percent_decode(part);
+        let (text, _, _) = encoding.decode(&bytes);
+        text.into_owned()
+    };
+    body.split(|byte| *byte == b'&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, |byte| *byte == b'=');
+            let name = parts.next().unwrap_or_default();
+            let value = parts.next().unwrap_or_default();
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(input: &[u8]) -> Cow<'_, [u8]> {
+    if !input.iter().any(|byte| matches!(byte, b'%' | b'+')) {
+        return Cow::Borrowed(input);
+    }
+    let hex = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < input.len() => {
+                match (hex(input[index + 1]), hex(input[index + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high << 4 | low);
+                        index += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    Cow::Owned(decoded)
+}
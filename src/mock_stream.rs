@@ -1,21 +1,41 @@
-use std::io::{self, Read, Result as IoResult, Write};
+use std::{
+    io::{self, Read, Result as IoResult, Write},
+    sync::{Arc, Mutex},
+};
 
 pub struct MockStream {
     data: String,
     pos: usize,
-    received_data:String
+    received_data: Arc<Mutex<String>>,
+}
+
+impl MockStream {
+    pub fn new(data: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            pos: 0,
+            received_data: Arc::default(),
+        }
+    }
+    pub fn received_data(&self) -> String {
+        self.received_data.lock().unwrap().clone()
+    }
+    // still readable once the stream itself was handed to `task_manager::handle`
+    pub fn received_data_handle(&self) -> Arc<Mutex<String>> {
+        Arc::clone(&self.received_data)
+    }
 }
 
 impl Write for MockStream{
     fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
         let received_string=String::from_utf8(buf.to_vec()).map_err( io::Error::other)?;
-        self.received_data.push_str(&received_string);
+        self.received_data.lock().unwrap().push_str(&received_string);
         Ok(buf.len())
 
     }
 
     fn flush(&mut self) -> IoResult<()> {
-        todo!()
+        Ok(())
     }
 }
 
@@ -0,0 +1,411 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Read, Result as IoResult, Write},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    body_reader::BodyReader,
+    extractor::FromRequestBody,
+    headers::HeaderMap,
+    media_type::{MediaType, parse_parameters},
+    parser::{header_parser::parse_header, http_message_parser::Request},
+};
+
+const MAX_PART_HEADERS_SIZE: usize = 16 * 1024;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MultipartLimits {
+    pub max_part_size: usize,
+    pub max_total_size: usize,
+    pub max_parts: usize,
+    // parts bigger than this are written to a temp file instead of being kept in memory
+    pub spill_threshold: usize,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_part_size: 5 * 1024 * 1024,
+            max_total_size: 10 * 1024 * 1024,
+            max_parts: 100,
+            spill_threshold: 1024 * 1024,
+        }
+    }
+}
+
+// removes the file when dropped unless it was persisted
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    fn create() -> IoResult<(Self, fs::File)> {
+        let mut suffix = [0; 16];
+        getrandom::fill(&mut suffix).map_err(io::Error::other)?;
+        let name: String = suffix.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let path = std::env::temp_dir().join(format!("upload-{}", name));
+        let file = fs::File::options().write(true).create_new(true).open(&path)?;
+        Ok((Self { path }, file))
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn persist(self, destination: impl AsRef<Path>) -> IoResult<()> {
+        if fs::rename(&self.path, &destination).is_err() {
+            // rename fails across filesystems
+            fs::copy(&self.path, &destination)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug)]
+pub enum FieldData {
+    Memory(Vec<u8>),
+    File(TempFile),
+}
+
+#[derive(Debug)]
+pub struct Field {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: HeaderMap,
+    data: FieldData,
+    size: usize,
+}
+
+impl Field {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    pub fn data(&self) -> &FieldData {
+        &self.data
+    }
+    pub fn into_data(self) -> FieldData {
+        self.data
+    }
+    // reads spilled parts back from disk
+    pub fn bytes(&self) -> IoResult<Cow<'_, [u8]>> {
+        match &self.data {
+            FieldData::Memory(bytes) => Ok(Cow::Borrowed(bytes)),
+            FieldData::File(file) => Ok(Cow::Owned(fs::read(file.path())?)),
+        }
+    }
+    pub fn text(&self) -> IoResult<String> {
+        String::from_utf8(self.bytes()?.into_owned()).map_err(io::Error::other)
+    }
+}
+
+pub struct Multipart {
+    fields: Vec<Field>,
+}
+
+impl Multipart {
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+    pub fn into_fields(self) -> Vec<Field> {
+        self.fields
+    }
+    // reads the body as it arrives, parts over the spill threshold go to disk chunk by chunk
+    pub fn parse(body: impl Read, boundary: &str, limits: MultipartLimits) -> Result<Self, MultipartError> {
+        let mut scanner = Scanner {
+            source: body,
+            buffered: Vec::new(),
+            total_read: 0,
+            limits,
+        };
+        let delimiter = format!("--{}", boundary).into_bytes();
+        scanner.skip_to(&delimiter)?;
+        let mut closing_delimiter = b"\r\n".to_vec();
+        closing_delimiter.extend_from_slice(&delimiter);
+        let mut fields = Vec::new();
+        loop {
+            scanner.fill_to(delimiter.len() + 2)?;
+            scanner.buffered.drain(..delimiter.len());
+            if scanner.buffered.starts_with(b"--") {
+                return Ok(Multipart { fields });
+            }
+            // transport padding may follow a boundary before its line break
+            loop {
+                let padding = scanner
+                    .buffered
+                    .iter()
+                    .take_while(|byte| matches!(byte, b' ' | b'\t'))
+                    .count();
+                scanner.buffered.drain(..padding);
+                if !scanner.buffered.is_empty() || !scanner.fill()? {
+                    break;
+                }
+            }
+            scanner.fill_to(2)?;
+            if !scanner.buffered.starts_with(b"\r\n") {
+                return Err(MultipartError::Malformed("boundary is not followed by a line break"));
+            }
+            if fields.len() == limits.max_parts {
+                return Err(MultipartError::TooManyParts);
+            }
+            let headers = scanner.part_headers()?;
+            let mut content = PartContent::new(part_disposition(&headers)?, limits);
+            scanner.stream_to(&closing_delimiter, &mut content)?;
+            fields.push(content.into_field(headers)?);
+        }
+    }
+}
+
+struct Scanner<R> {
+    source: R,
+    // read from the source but not consumed yet, never much more than one read
+    buffered: Vec<u8>,
+    total_read: usize,
+    limits: MultipartLimits,
+}
+
+impl<R: Read> Scanner<R> {
+    // false once the body has ended
+    fn fill(&mut self) -> Result<bool, MultipartError> {
+        let mut buf = [0; 8192];
+        let n = self.source.read(&mut buf).map_err(MultipartError::Body)?;
+        self.total_read += n;
+        if self.total_read > self.limits.max_total_size {
+            return Err(MultipartError::TotalTooLarge);
+        }
+        self.buffered.extend_from_slice(&buf[..n]);
+        Ok(n > 0)
+    }
+    fn fill_to(&mut self, len: usize) -> Result<bool, MultipartError> {
+        while self.buffered.len() < len {
+            if !self.fill()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+    // drops the preamble, the buffer then starts with the delimiter
+    fn skip_to(&mut self, delimiter: &[u8]) -> Result<(), MultipartError> {
+        loop {
+            if let Some(index) = find(&self.buffered, delimiter) {
+                self.buffered.drain(..index);
+                return Ok(());
+            }
+            let keep = self.buffered.len().min(delimiter.len() - 1);
+            self.buffered.drain(..self.buffered.len() - keep);
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("missing opening boundary"));
+            }
+        }
+    }
+    // expects the buffer to start with the line break after the boundary and leaves the
+    // line break that ends the headers in it
+    fn part_headers(&mut self) -> Result<HeaderMap, MultipartError> {
+        loop {
+            if let Some(headers_end) = find(&self.buffered, b"\r\n\r\n") {
+                let headers = parse_part_headers(&self.buffered[2.min(headers_end)..headers_end])?;
+                self.buffered.drain(..headers_end + 2);
+                return Ok(headers);
+            }
+            if self.buffered.len() > MAX_PART_HEADERS_SIZE {
+                return Err(MultipartError::Malformed("part headers are too long"));
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("part headers are not terminated"));
+            }
+        }
+    }
+    // hands over everything up to the closing delimiter and leaves the boundary that
+    // follows it in the buffer
+    fn stream_to(&mut self, closing_delimiter: &[u8], content: &mut PartContent) -> Result<(), MultipartError> {
+        // the line break that ended the headers, it may double as the one before the delimiter
+        let mut skip = 2;
+        loop {
+            if let Some(content_end) = find(&self.buffered, closing_delimiter) {
+                content.write(&self.buffered[skip.min(content_end)..content_end])?;
+                self.buffered.drain(..content_end + 2);
+                return Ok(());
+            }
+            // the tail may be the start of a delimiter split across reads
+            let safe = self.buffered.len().saturating_sub(closing_delimiter.len() - 1);
+            if safe > skip {
+                content.write(&self.buffered[skip..safe])?;
+                self.buffered.drain(..safe);
+                skip = 0;
+            }
+            if !self.fill()? {
+                return Err(MultipartError::Malformed("part is not closed by a boundary"));
+            }
+        }
+    }
+}
+
+struct Disposition {
+    name: String,
+    filename: Option<String>,
+}
+
+// collects a part's bytes in memory until they pass the spill threshold
+struct PartContent {
+    disposition: Disposition,
+    limits: MultipartLimits,
+    data: PartData,
+    size: usize,
+}
+
+enum PartData {
+    Memory(Vec<u8>),
+    File(TempFile, fs::File),
+}
+
+impl PartContent {
+    fn new(disposition: Disposition, limits: MultipartLimits) -> Self {
+        Self {
+            disposition,
+            limits,
+            data: PartData::Memory(Vec::new()),
+            size: 0,
+        }
+    }
+    fn write(&mut self, bytes: &[u8]) -> Result<(), MultipartError> {
+        self.size += bytes.len();
+        if self.size > self.limits.max_part_size {
+            return Err(MultipartError::PartTooLarge(self.disposition.name.clone()));
+        }
+        match &mut self.data {
+            PartData::Memory(data) if self.size > self.limits.spill_threshold => {
+                let (temp_file, mut file) = TempFile::create()?;
+                file.write_all(data)?;
+                file.write_all(bytes)?;
+                self.data = PartData::File(temp_file, file);
+            }
+            PartData::Memory(data) => data.extend_from_slice(bytes),
+            PartData::File(_, file) => file.write_all(bytes)?,
+        }
+        Ok(())
+    }
+    fn into_field(self, headers: HeaderMap) -> Result<Field, MultipartError> {
+        let data = match self.data {
+            PartData::Memory(data) => FieldData::Memory(data),
+            PartData::File(temp_file, mut file) => {
+                file.flush()?;
+                FieldData::File(temp_file)
+            }
+        };
+        Ok(Field {
+            name: self.disposition.name,
+            filename: self.disposition.filename,
+            content_type: headers.get("content-type").map(str::to_string),
+            headers,
+            data,
+            size: self.size,
+        })
+    }
+}
+
+impl FromRequestBody for Multipart {
+    const STREAMS_BODY: bool = true;
+    type Error = MultipartError;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        let media_type = request
+            .header("content-type")
+            .and_then(MediaType::parse)
+            .filter(|media_type| media_type.is("multipart", "form-data"))
+            .ok_or(MultipartError::ContentTypeMisMatch)?;
+        let boundary = media_type
+            .parameter("boundary")
+            .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+            .ok_or(MultipartError::MissingBoundary)?;
+        let limits = request
+            .routing()
+            .state::<MultipartLimits>()
+            .map(|limits| *limits)
+            .unwrap_or_default();
+        let Ok(body) = BodyReader::from_request_body(request);
+        Multipart::parse(body, boundary, limits)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MultipartError {
+    #[error("content type is not multipart/form-data")]
+    ContentTypeMisMatch,
+    #[error("multipart boundary is missing")]
+    MissingBoundary,
+    #[error("malformed multipart body: {0}")]
+    Malformed(&'static str),
+    #[error("part {0} is too large")]
+    PartTooLarge(String),
+    #[error("multipart body is too large")]
+    TotalTooLarge,
+    #[error("too many parts in multipart body")]
+    TooManyParts,
+    #[error("could not read multipart body: {0}")]
+    Body(io::Error),
+    #[error("could not store part: {0}")]
+    Io(#[from] io::Error),
+}
+
+fn part_disposition(headers: &HeaderMap) -> Result<Disposition, MultipartError> {
+    let disposition = headers
+        .get("content-disposition")
+        .ok_or(MultipartError::Malformed("part has no content disposition"))?;
+    let mut disposition_parts = disposition.splitn(2, ';');
+    if !disposition_parts
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .eq_ignore_ascii_case("form-data")
+    {
+        return Err(MultipartError::Malformed("part disposition is not form-data"));
+    }
+    let parameters = parse_parameters(disposition_parts.next().unwrap_or_default());
+    let parameter = |wanted: &str| {
+        parameters
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.clone())
+    };
+    Ok(Disposition {
+        name: parameter("name").ok_or(MultipartError::Malformed("part has no name"))?,
+        filename: parameter("filename"),
+    })
+}
+
+fn parse_part_headers(data: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let text = std::str::from_utf8(data).map_err(|_| MultipartError::Malformed("part headers are not utf-8"))?;
+    let mut headers = HeaderMap::new();
+    for line in text.split("\r\n").filter(|line| !line.is_empty()) {
+        let (key, value) = parse_header(line).map_err(|_| MultipartError::Malformed("invalid part header"))?;
+        headers.append(key, value);
+    }
+    Ok(headers)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
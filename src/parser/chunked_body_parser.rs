@@ -7,6 +7,7 @@ enum BodyChunkPart {
     #[default]
     DataSizePart,
     DataContentPart,
+    LastChunk,
 }
 #[derive(Default)]
 pub struct BodyParser{
@@ -24,6 +25,7 @@ impl BodyParser{
             BodyChunkPart::DataContentPart => {
                 self.parse_chunked_body_content(data)
             },
+            BodyChunkPart::LastChunk => Err(ParseError::HeadersDone),
         }
 
     }
@@ -41,34 +43,41 @@ impl BodyParser{
             .read_to_string(&mut body_chunk_size_str)
             .map_err(|_| ParseError::OtherError("error in reading string to cursor".to_string()))?;
 
+        // chunk extensions after `;` carry no meaning for us
+        let body_chunk_size_str = body_chunk_size_str
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim();
         let bytes_to_be_retrieved =
-            usize::from_str_radix(&body_chunk_size_str, 16).map_err(|_| {
+            usize::from_str_radix(body_chunk_size_str, 16).map_err(|_| {
                 ParseError::OtherError("error in parsing from hexadecimal string".to_string())
             })?;
         if bytes_to_be_retrieved == 0 {
-            return Err(ParseError::HeadersDone);
+            self.body_chunk_part = BodyChunkPart::LastChunk;
+            return Ok(next_body_data_index);
         }
         self.bytes_to_retrieve = bytes_to_be_retrieved;
         self.set_body_chunk_part();
         Ok(next_body_data_index)
     }
     fn parse_chunked_body_content(&mut self,data:&[u8]) -> Result<usize, ParseError> {
-        let next_body_data_size_index = match find_field_line_index(data) {
-            Some(index) => index,
+        // the chunk data may itself contain CRLF, so the chunk size decides where it ends
+        let next_body_data_size_index = self
+            .bytes_to_retrieve
+            .checked_add(2)
+            .ok_or_else(|| ParseError::OtherError("chunk size is too large".to_string()))?;
+        match data.get(self.bytes_to_retrieve..next_body_data_size_index) {
+            Some(b"\r\n") => {}
+            Some(_) => {
+                return Err(ParseError::OtherError(
+                    "wrong transfer chunk encoding".to_string(),
+                ));
+            }
             None => {
                 return Err(ParseError::NotEnoughBytes);
             }
-        };
-        // let mut body_chunk_size_str = String::new();
-        // let mut cursor = Cursor::new(&data[..next_body_data_size_index - 2]);
-
-        // cursor
-        //     .read_to_string(&mut body_chunk_size_str)
-        //     .map_err(|_| ParseError::OtherError("error in reading string to cursor".to_string()))?;
-
-        // println!("parsed string is \n {body_chunk_size_str}");
-        // println!("parsed index is {next_body_data_size_index}");
-
+        }
         self.add_chunk_to_body(data)
             .map_err(|err| ParseError::OtherError(err.to_owned()))?;
         self.set_body_chunk_part();
@@ -78,6 +87,7 @@ impl BodyParser{
         match self.body_chunk_part {
             BodyChunkPart::DataSizePart => self.body_chunk_part = BodyChunkPart::DataContentPart,
             BodyChunkPart::DataContentPart => self.body_chunk_part = BodyChunkPart::DataSizePart,
+            BodyChunkPart::LastChunk => {}
         }
     }
     fn add_chunk_to_body(&mut self,data:&[u8]) -> Result<(), &str> {
@@ -94,6 +104,14 @@ impl BodyParser{
     pub fn add_to_body(&mut self,data:&[u8]) {
         self.body.extend_from_slice(data);
     }
+    // what the body will have grown to once the chunk being read is complete, a size past
+    // usize::MAX saturates so it is still over any limit
+    pub fn expected_body_len(&self)->usize{
+        match self.body_chunk_part {
+            BodyChunkPart::DataContentPart => self.body.len().saturating_add(self.bytes_to_retrieve),
+            _ => self.body.len(),
+        }
+    }
     pub fn get_body(self)->Vec<u8>{
         self.body
     }
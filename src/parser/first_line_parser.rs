@@ -1,6 +1,6 @@
 use std::io::{Cursor, Read};
 
-use crate::parser::http_message_parser::find_field_line_index;
+use crate::parser::{header_parser::is_token, http_message_parser::find_field_line_index};
 
 pub enum FirstLineParseError {
     FirstLinePartsMissing,
@@ -102,13 +102,13 @@ impl FirstLineResponseParser {
 }
 
 pub fn parse_request_line(request_line: &str) -> Result<RequestLine, FirstLineParseError> {
-    let http_verbs = ["GET", "POST", "PATCH", "DELETE", "PUT", "OPTIONS"];
     let broken_string = request_line.split(' ').collect::<Vec<&str>>();
     if broken_string.len() < 3 {
         return Err(FirstLineParseError::FirstLinePartsMissing);
     }
+    // extension methods are allowed as long as they are a valid token
     let mut http_verb = String::new();
-    if http_verbs.contains(&broken_string[0]) {
+    if !broken_string[0].is_empty() && is_token(broken_string[0]) {
         http_verb.push_str(broken_string[0]);
     } else {
         return Err(FirstLineParseError::InvalidHttpMethod);
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, io::{Cursor, Read}};
+use std::io::{Cursor, Read};
 
-use crate::parser::http_message_parser::find_field_line_index;
+use crate::{headers::HeaderMap, parser::http_message_parser::find_field_line_index};
 
 
 pub enum HeaderParseError {
@@ -11,8 +11,8 @@ pub enum HeaderParseError {
 }
 #[derive(Default)]
 pub struct HeaderParser{
-    headers: HashMap<String, String>,
-    trailer_headers: HashMap<String, String>,
+    headers: HeaderMap,
+    trailer_headers: HeaderMap,
 }
 impl HeaderParser{
     pub fn parse_header(&mut self,data:&[u8]) -> Result<usize, HeaderParseError> {
@@ -46,33 +46,42 @@ impl HeaderParser{
         self.set_trailer_headers(key, value);
         Ok(next_field_line_index)
     }
+    // every field line is kept as sent, repeated names are not joined so Set-Cookie survives proxying
     fn set_headers(&mut self, key: String, value: String) {
-        self.headers
-            .entry(key)
-            .and_modify(|existing| {
-                existing.push(','); // HTTP header values separated by comma-space
-                existing.push_str(&value);
-            })
-            .or_insert(value);
+        self.headers.append(key, value);
     }
     fn set_trailer_headers(&mut self, key: String, value: String) {
-        self.trailer_headers
-            .entry(key)
-            .and_modify(|existing| {
-                existing.push(','); // HTTP header values separated by comma-space
-                existing.push_str(&value);
-            })
-            .or_insert(value);
+        self.trailer_headers.append(key, value);
+    }
+    // differing Content-Length lines, or one next to Transfer-Encoding, would let this end
+    // and the next hop disagree on where the message stops
+    pub fn check_message_framing(&self) -> Result<(), &'static str> {
+        let mut content_lengths = self.headers.get_all("content-length");
+        if let Some(first) = content_lengths.next()
+            && content_lengths.any(|content_length| content_length != first)
+        {
+            return Err("conflicting content length headers");
+        }
+        if self.headers.contains_key("content-length") && self.headers.contains_key("transfer-encoding") {
+            return Err("content length sent together with transfer encoding");
+        }
+        Ok(())
     }
-    pub fn header(&self, key: &str) -> Option<&String> {
+    pub fn header(&self, key: &str) -> Option<&str> {
         self.headers.get(key)
     }
-    pub fn get_headers(self)->HashMap<String,String>{
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers.get_all(key)
+    }
+    pub fn get_headers(self)->HeaderMap{
         self.headers
     }
-    pub fn get_headers_ref(&self)->&HashMap<String,String>{
+    pub fn get_headers_ref(&self)->&HeaderMap{
         &self.headers
     }
+    pub fn get_trailer_headers_ref(&self)->&HeaderMap{
+        &self.trailer_headers
+    }
 }
 
 
@@ -88,7 +97,7 @@ pub fn parse_header(header_field: &str) -> Result<(String, String), HeaderParseE
             key
         )));
     }
-    if !is_valid_field_name(key) {
+    if !is_token(key) {
         return Err(HeaderParseError::InvalidHeader(format!(
             "the key ``{}`` contains invalid characters",
             key
@@ -96,12 +105,12 @@ pub fn parse_header(header_field: &str) -> Result<(String, String), HeaderParseE
     }
     let value = broken_parts[1..].join(":");
     Ok((
-        key.to_lowercase().trim().to_string(),
+        key.trim().to_string(),
         value.trim().to_string(),
     ))
 }
 
-fn is_valid_field_name(s: &str) -> bool {
+pub fn is_token(s: &str) -> bool {
     s.chars().all(|c| {
         c.is_ascii_alphanumeric()
             || matches!(
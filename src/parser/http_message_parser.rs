@@ -1,8 +1,9 @@
 use std::{
-    collections::HashMap,
-    io::{ Read, Write}, sync::Arc,
+    io::ErrorKind, sync::Arc,
+    time::{Duration, Instant},
 };
-use crate::{parser::{
+use thiserror::Error;
+use crate::{extensions::Extensions, headers::HeaderMap, parser::{
     chunked_body_parser::BodyParser,
     first_line_parser::{
         FirstLineParseError, FirstLineParser,
@@ -10,7 +11,7 @@ use crate::{parser::{
     },
     front_from_body_parser::parse_front,
     header_parser::{ HeaderParseError, HeaderParser},
-}, routing::{HttpVerb, RoutingMap}};
+}, response::StatusCode, routing::{HttpVerb, RoutingMap}, stream::HttpStream};
 
 pub enum ParseError {
     NotEnoughBytes,
@@ -20,6 +21,84 @@ pub enum ParseError {
     InvalidHeader(String),
     HeadersDone,
 }
+#[derive(Error, Debug, PartialEq)]
+pub enum MessageParseError {
+    #[error("connection closed before a message was sent")]
+    ConnectionClosed,
+    #[error("timed out waiting for the message")]
+    Timeout,
+    #[error("error reading stream")]
+    ReadError,
+    #[error("request line is too long")]
+    UriTooLong,
+    #[error("request header fields are too large")]
+    HeadersTooLarge,
+    #[error("request body is too large")]
+    PayloadTooLarge,
+    #[error("{0}")]
+    Malformed(String),
+}
+
+impl MessageParseError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MessageParseError::Timeout => StatusCode::RequestTimeout,
+            MessageParseError::UriTooLong => StatusCode::UriTooLong,
+            MessageParseError::HeadersTooLarge => StatusCode::RequestHeaderFieldsTooLarge,
+            MessageParseError::PayloadTooLarge => StatusCode::PayloadTooLarge,
+            _ => StatusCode::BadRequest,
+        }
+    }
+}
+
+impl From<String> for MessageParseError {
+    fn from(cause: String) -> Self {
+        MessageParseError::Malformed(cause)
+    }
+}
+
+impl From<&str> for MessageParseError {
+    fn from(cause: &str) -> Self {
+        MessageParseError::Malformed(cause.to_string())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ReadTimeouts {
+    pub header_read: Duration,
+    pub body_read: Duration,
+    pub header_deadline: Duration,
+}
+
+impl Default for ReadTimeouts {
+    fn default() -> Self {
+        Self {
+            header_read: Duration::from_secs(10),
+            body_read: Duration::from_secs(30),
+            header_deadline: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ParserLimits {
+    pub max_request_line_length: usize,
+    pub max_header_bytes: usize,
+    pub max_header_count: usize,
+    pub max_body_size: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        Self {
+            max_request_line_length: 8 * 1024,
+            max_header_bytes: 64 * 1024,
+            max_header_count: 100,
+            max_body_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
 pub enum ParsingState {
     FrontSeparateBody,
     FirstLine,
@@ -32,6 +111,8 @@ pub enum ParsingState {
     ParsingDone,
 }
 
+pub type StreamedBody<T> = Box<dyn Fn(&T) -> bool>;
+
 pub struct Parser<P: FirstLineParser> {
     first_line_parser: P,
     header_parser: HeaderParser,
@@ -40,45 +121,79 @@ pub struct Parser<P: FirstLineParser> {
     current_position: usize,
     data: Vec<u8>,
     parsing_state: ParsingState,
+    message_end: Option<usize>,
+    timeouts: ReadTimeouts,
+    started_at: Instant,
+    limits: ParserLimits,
+    header_count: usize,
+    // decides from the first line whether the body is left on the stream for a BodyReader
+    streamed_body: Option<StreamedBody<P::HttpType>>,
 }
 impl<P: FirstLineParser> Parser<P> {
     pub fn new(
         first_line_parser: P,
     ) -> Parser<P> {
+        Self::with_buffered_data(first_line_parser, Vec::with_capacity(1024))
+    }
+    pub fn with_buffered_data(first_line_parser: P, data: Vec<u8>) -> Parser<P> {
         Parser {
             first_line_parser,
             header_parser:HeaderParser::default(),
             body_parser:BodyParser::default(),
             body_cursor: 0,
             current_position: 0,
-            data: Vec::with_capacity(1024),
+            data,
             parsing_state: ParsingState::FrontSeparateBody,
+            message_end: None,
+            timeouts: ReadTimeouts::default(),
+            started_at: Instant::now(),
+            limits: ParserLimits::default(),
+            header_count: 0,
+            streamed_body: None,
         }
     }
-    pub fn parse<S: Write + Read>(mut self, stream: &mut S) -> Result<Payload<P::HttpType> , String> {
+    pub fn with_timeouts(mut self, timeouts: ReadTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    pub fn with_streamed_body(mut self, streamed_body: impl Fn(&P::HttpType) -> bool + 'static) -> Self {
+        self.streamed_body = Some(Box::new(streamed_body));
+        self
+    }
+    pub fn parse<S: HttpStream>(mut self, stream: &mut S) -> Result<Payload<P::HttpType> , MessageParseError> {
         let mut buf = [0; 1024];
-        let mut n = stream.read(&mut buf).map_err(|err| {
-            println!("error in reading {}", err);
-            "error reading stream".to_string()
-        })?;
-        if n == 0 {
-            return Err("false alarm".to_string());
+        if self.data.is_empty() {
+            // the connection going quiet or away before a new message starts is not an error
+            let n = stream.read(&mut buf).map_err(|err| match err.kind() {
+                ErrorKind::WouldBlock | ErrorKind::TimedOut => MessageParseError::ConnectionClosed,
+                _ => {
+                    println!("error in reading {}", err);
+                    MessageParseError::ReadError
+                }
+            })?;
+            if n == 0 {
+                return Err(MessageParseError::ConnectionClosed);
+            }
+            self.add_to_data(&buf[..n]);
         }
+        self.started_at = Instant::now();
 
-        self.add_to_data(&buf[..n]);
         loop {
             match self.parsing_state {
                 ParsingState::FrontSeparateBody => {
                     match parse_front(&self.data) {
                         Ok(body_cursor) => {
+                            self.check_head_size(Some(body_cursor))?;
                             self.body_cursor = body_cursor;
                             self.parsing_state = ParsingState::FirstLine;
                         }
                         Err(_) => {
-                            n = stream
-                                .read(&mut buf)
-                                .map_err(|_| "error reading stream".to_string())?;
-                            self.add_to_data(&buf[..n]);
+                            self.check_head_size(None)?;
+                            self.read_more(stream, &mut buf)?;
                         }
                     };
                 }
@@ -116,10 +231,18 @@ impl<P: FirstLineParser> Parser<P> {
                     {
                         Ok(offset) => {
                             self.current_position += offset;
+                            self.header_count += 1;
+                            if self.header_count > self.limits.max_header_count {
+                                return Err(MessageParseError::HeadersTooLarge);
+                            }
                         }
                         Err(err) => match err {
                             HeaderParseError::HeadersDone => {
                                 self.current_position += 2;
+                                self.header_parser.check_message_framing()?;
+                                if self.streams_body() {
+                                    return self.create_streamed_payload();
+                                }
                                 let content_length = match self
                                     .header_parser
                                     .header("content-length")
@@ -132,7 +255,7 @@ impl<P: FirstLineParser> Parser<P> {
                                                 None => {
                                                     self.parsing_state =
                                                         ParsingState::BodyContentLength;
-
+                                                    self.message_end = Some(self.body_cursor);
                                                     return Ok(self.create_parsed_payload());
                                                 }
                                             };
@@ -140,6 +263,7 @@ impl<P: FirstLineParser> Parser<P> {
                                             self.parsing_state = ParsingState::BodyChunked;
                                         } else {
                                             self.parsing_state = ParsingState::BodyContentLength;
+                                            self.message_end = Some(self.body_cursor);
                                             return Ok(self.create_parsed_payload());
                                         }
                                         continue;
@@ -149,27 +273,26 @@ impl<P: FirstLineParser> Parser<P> {
                                 .map_err(|_| {
                                     "coluld not parse content length header".to_string()
                                 })?;
+                                if content_length > self.limits.max_body_size {
+                                    return Err(MessageParseError::PayloadTooLarge);
+                                }
                                 self.parsing_state = ParsingState::BodyContentLength;
                                 if self.body_len() >= content_length {
-                                    self.body_parser.add_to_body(&self.data[self.body_cursor..]);
-                                    return Ok(self.create_parsed_payload());
+                                    return Ok(self.finish_content_length_body(content_length));
                                 }
                             }
                             HeaderParseError::OtherError => {
                                 return Err("another error".into());
                             }
                             HeaderParseError::InvalidHeader(cause) => {
-                                return Err(cause);
+                                return Err(cause.into());
                             }
                             HeaderParseError::NotEnoughBytes => continue,
                         },
                     };
                 }
                 ParsingState::BodyContentLength => {
-                    n = stream
-                        .read(&mut buf)
-                        .map_err(|_| "error reading stream".to_string())?;
-                    self.add_to_data(&buf[..n]);
+                    self.read_more(stream, &mut buf)?;
                     let content_length = self
                         .header_parser
                         .header("content-length")
@@ -177,8 +300,7 @@ impl<P: FirstLineParser> Parser<P> {
                         .parse::<usize>()
                         .map_err(|_| "could not parse content length from header".to_string())?;
                     if self.body_len() >= content_length {
-                        self.body_parser.add_to_body(&self.data[self.body_cursor..]);
-                        return Ok(self.create_parsed_payload());
+                        return Ok(self.finish_content_length_body(content_length));
                     }
                 }
                 ParsingState::BodyChunked => {
@@ -188,27 +310,19 @@ impl<P: FirstLineParser> Parser<P> {
                     {
                         Ok(offset) => {
                             self.current_position += offset;
+                            if self.body_parser.expected_body_len() > self.limits.max_body_size {
+                                return Err(MessageParseError::PayloadTooLarge);
+                            }
                         }
                         Err(err) => match err {
                             ParseError::NotEnoughBytes => {
-                                n = stream
-                                    .read(&mut buf)
-                                    .map_err(|_| "error reading stream".to_string())?;
-                                self.add_to_data(&buf[..n]);
+                                self.read_more(stream, &mut buf)?;
                             }
+                            // the last chunk is always followed by an optional trailer section
                             ParseError::HeadersDone => {
-                                match self.header_parser.header("Trailer") {
-                                Some(_) => {
-                                    self.parsing_state = ParsingState::BodyDone;
-                                    self.parsing_state = ParsingState::TrailerHeaders;
-                                }
-                                None => {
-                                    self.parsing_state = ParsingState::ParsingDone;
-                                    return Ok(self.create_parsed_payload());
-                                }
+                                self.parsing_state = ParsingState::TrailerHeaders;
                             }
-
-                            },
+                            ParseError::OtherError(cause) => return Err(cause.into()),
                             _ => return Ok(self.create_parsed_payload()),
                         },
                     }
@@ -226,17 +340,16 @@ impl<P: FirstLineParser> Parser<P> {
                             self.parsing_state = ParsingState::TrailerHeadersDone;
                         }
                         HeaderParseError::NotEnoughBytes => {
-                            n = stream
-                                .read(&mut buf)
-                                .map_err(|_| "error reading stream".to_string())?;
-                            self.add_to_data(&buf[..n]);
+                            self.read_more(stream, &mut buf)?;
                         }
                         _ => {
-                            return Err("an error writing to cursor occurred".to_string());
+                            return Err("an error writing to cursor occurred".into());
                         }
                     },
                 },
                 ParsingState::TrailerHeadersDone => {
+                    self.current_position += 2;
+                    self.message_end = Some(self.current_position);
                     return Ok(self.create_parsed_payload());
                 }
                 ParsingState::ParsingDone => {
@@ -246,17 +359,90 @@ impl<P: FirstLineParser> Parser<P> {
         }
     }
 
+    fn read_more<S: HttpStream>(&mut self, stream: &mut S, buf: &mut [u8]) -> Result<(), MessageParseError> {
+        let read_timeout = match self.parsing_state {
+            ParsingState::FrontSeparateBody | ParsingState::FirstLine | ParsingState::Headers => {
+                // a client trickling in header bytes must still finish within the deadline
+                let remaining = self
+                    .timeouts
+                    .header_deadline
+                    .saturating_sub(self.started_at.elapsed());
+                if remaining.is_zero() {
+                    return Err(MessageParseError::Timeout);
+                }
+                self.timeouts.header_read.min(remaining)
+            }
+            _ => self.timeouts.body_read,
+        };
+        stream
+            .set_read_timeout(Some(read_timeout))
+            .map_err(|_| MessageParseError::ReadError)?;
+        let n = stream.read(buf).map_err(|err| match err.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => MessageParseError::Timeout,
+            _ => MessageParseError::ReadError,
+        })?;
+        if n == 0 {
+            return Err("connection closed before the message was complete".into());
+        }
+        self.add_to_data(&buf[..n]);
+        Ok(())
+    }
+    fn check_head_size(&self, head_end: Option<usize>) -> Result<(), MessageParseError> {
+        let head = &self.data[..head_end.unwrap_or(self.data.len())];
+        let request_line_end = find_field_line_index(head);
+        let request_line_length = request_line_end.map_or(head.len(), |index| index - 2);
+        if request_line_length > self.limits.max_request_line_length {
+            return Err(MessageParseError::UriTooLong);
+        }
+        let header_bytes = head.len() - request_line_end.unwrap_or(head.len());
+        if header_bytes > self.limits.max_header_bytes {
+            return Err(MessageParseError::HeadersTooLarge);
+        }
+        Ok(())
+    }
     fn add_to_data(&mut self, buf: &[u8]) {
         self.data.extend_from_slice(buf);
     }
     fn body_len(&self) -> usize {
         self.data.len() - self.body_cursor
     }
+    fn finish_content_length_body(mut self, content_length: usize) -> Payload<P::HttpType> {
+        let body_end = self.body_cursor + content_length;
+        self.body_parser
+            .add_to_body(&self.data[self.body_cursor..body_end]);
+        self.message_end = Some(body_end);
+        self.create_parsed_payload()
+    }
+    fn streams_body(&self) -> bool {
+        self.streamed_body
+            .as_ref()
+            .is_some_and(|streamed_body| streamed_body(&self.first_line_parser.get_first_line_ref()))
+    }
+    // stops at the end of the head, the leftover is whatever part of the body was already read
+    fn create_streamed_payload(mut self) -> Result<Payload<P::HttpType>, MessageParseError> {
+        if let Some(content_length) = self.header_parser.header("content-length") {
+            let content_length = content_length
+                .parse::<usize>()
+                .map_err(|_| "could not parse content length header".to_string())?;
+            if content_length > self.limits.max_body_size {
+                return Err(MessageParseError::PayloadTooLarge);
+            }
+        }
+        self.message_end = Some(self.body_cursor);
+        let mut payload = self.create_parsed_payload();
+        payload.body_streamed = true;
+        Ok(payload)
+    }
     pub fn create_parsed_payload(self) ->Payload<P::HttpType>  {
+        let leftover = self
+            .message_end
+            .map(|message_end| self.data[message_end..].to_vec());
         Payload{
             first_line: self.first_line_parser.get_first_line(),
             headers: self.header_parser.get_headers(),
             body: self.body_parser.get_body(),
+            leftover,
+            body_streamed: false,
         }
     }
 }
@@ -264,13 +450,25 @@ impl<P: FirstLineParser> Parser<P> {
 
 pub struct Payload<T>{
     first_line:T,
-    headers:HashMap<String,String>,
+    headers:HeaderMap,
     body:Vec<u8>,
-    
+    leftover:Option<Vec<u8>>,
+    body_streamed:bool,
+}
+impl<T> Payload<T> {
+    // the body is still on the stream, starting with the leftover bytes
+    pub fn body_streamed(&self) -> bool {
+        self.body_streamed
+    }
+    // bytes read past the end of this message, `None` when the end of the
+    // message could not be determined and the connection cannot be reused
+    pub fn take_leftover(&mut self) -> Option<Vec<u8>> {
+        self.leftover.take()
+    }
 }
 impl Payload<RequestLine>{
     pub fn from(self,routing:Arc<RoutingMap>) -> Request {
-        Request { request_line: self.first_line, headers: self.headers, body: self.body,routing }
+        Request { request_line: self.first_line, headers: self.headers, body: self.body,routing, extensions: Extensions::default() }
     }
 }
 
@@ -281,18 +479,21 @@ impl From<Payload<ResponseLine>> for  Response{
 }
 pub struct Request {
     request_line: RequestLine,
-    headers: HashMap<String, String>,
+    headers: HeaderMap,
     body: Vec<u8>,
-    routing:Arc<RoutingMap>
+    routing:Arc<RoutingMap>,
+    // per request values added by middleware, e.g. the session
+    extensions: Extensions,
 }
 
 impl Request {
-    pub fn new(request_line: RequestLine, headers: HashMap<String, String>, body: Vec<u8>,routing:Arc<RoutingMap>) -> Self {
+    pub fn new(request_line: RequestLine, headers: HeaderMap, body: Vec<u8>,routing:Arc<RoutingMap>) -> Self {
         Self {
             request_line,
             headers,
             body,
-            routing
+            routing,
+            extensions: Extensions::default(),
         }
     }
     pub fn routing(&self)->Arc<RoutingMap>{
@@ -300,14 +501,7 @@ impl Request {
         Arc::clone(routing)
     }
     pub fn request_method(&self) -> HttpVerb {
-        match self.request_line.method(){
-            "GET"=>HttpVerb::GET,
-            "POST"=>HttpVerb::POST,
-            "PUT"=>HttpVerb::PUT,
-            "PATCH"=>HttpVerb::PATCH,
-            "DELETE"=>HttpVerb::DELETE,
-            _=>HttpVerb::OPTIONS
-        }
+        HttpVerb::from(self.request_line.method())
     }
     pub fn request_path(&self) -> &str {
         let val=self.request_line.request_target().split('?').collect::<Vec<&str>>();
@@ -329,28 +523,54 @@ impl Request {
         }
     }
 
-    pub fn header(&self, header: &str) -> Option<&String> {
+    pub fn http_version(&self) -> &str {
+        self.request_line.http_version()
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        let connection_tokens: Vec<String> = self
+            .header("connection")
+            .map(|value| value.split(',').map(|token| token.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        if connection_tokens.iter().any(|token| token == "close") {
+            return false;
+        }
+        if self.http_version() == "1.0" {
+            return connection_tokens.iter().any(|token| token == "keep-alive");
+        }
+        true
+    }
+
+    pub fn header(&self, header: &str) -> Option<&str> {
         self.headers.get(header)
     }
 
-    pub fn headers(&self) -> &HashMap<String, String> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
     pub fn body(&self) -> &[u8] {
         &self.body
     }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
 pub struct Response {
     response_line: ResponseLine,
-    headers: HashMap<String, String>,
+    headers: HeaderMap,
     body: Vec<u8>,
 }
 impl Response {
     pub fn new(
         response_line: ResponseLine,
-        headers: HashMap<String, String>,
+        headers: HeaderMap,
         body: Vec<u8>,
     ) -> Self {
         Self {
@@ -367,7 +587,7 @@ impl Response {
         &self.response_line
     }
 
-    pub fn headers(&self) -> &HashMap<String, String> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 }
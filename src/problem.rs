@@ -0,0 +1,66 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::response::{
+    ContentType, IntoResponse, Response, StatusCode, get_common_headers_with_content_type_header,
+};
+
+// an RFC 9457 problem details object, the body of every extractor rejection
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub status: StatusCode,
+    pub problem_type: String,
+    pub title: String,
+    pub detail: Option<String>,
+    pub instance: Option<String>,
+    // extension members serialized next to the standard ones, e.g. the failing field
+    pub members: Map<String, Value>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            problem_type: "about:blank".to_string(),
+            title: status.reason_phrase().to_string(),
+            detail: Some(detail.into()),
+            instance: None,
+            members: Map::new(),
+        }
+    }
+    // merges the fields of a serializable struct or map into the extension members
+    pub fn with_members(mut self, members: impl Serialize) -> Self {
+        if let Ok(Value::Object(members)) = serde_json::to_value(members) {
+            self.members.extend(members);
+        }
+        self
+    }
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("type".to_string(), Value::from(self.problem_type.as_str()));
+        object.insert("title".to_string(), Value::from(self.title.as_str()));
+        object.insert("status".to_string(), Value::from(self.status.as_u16()));
+        if let Some(detail) = &self.detail {
+            object.insert("detail".to_string(), Value::from(detail.as_str()));
+        }
+        if let Some(instance) = &self.instance {
+            object.insert("instance".to_string(), Value::from(instance.as_str()));
+        }
+        // extensions can't shadow the standard members
+        for (key, value) in &self.members {
+            object.entry(key.as_str()).or_insert_with(|| value.clone());
+        }
+        Value::Object(object)
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let body = self.to_json().to_string().into_bytes();
+        let headers = get_common_headers_with_content_type_header(&body, ContentType::ApplicationProblemJson);
+        let mut response = Response::new(self.status.status_message(), self.status, headers, body);
+        // kept on the response so a server wide rejection renderer can rebuild it
+        response.extensions_mut().insert(self);
+        response
+    }
+}
@@ -1,4 +1,4 @@
-use crate::parser::{
+use crate::{headers::HeaderMap, parser::{
     chunked_body_parser::BodyParser,
     first_line_parser::{
         FirstLineParseError, FirstLineParser, RequestLine, ResponseLine
@@ -6,19 +6,17 @@ use crate::parser::{
     front_from_body_parser::parse_front,
     header_parser::{HeaderParseError, HeaderParser},
     http_message_parser::{ParseError, ParsingState},
-};
+}};
 use std::{
-    collections::HashMap,
     io::{Read, Result as IoResult, Write},
-    net::TcpStream,
 };
 
 pub trait ProxyHeadersSender<T> {
     fn send_first_line_and_headers(
         &self,
-        remote_host_stream: &mut TcpStream,
+        remote_host_stream: &mut dyn Write,
         first_line: T,
-        headers: &HashMap<String, String>,
+        headers: &HeaderMap,
     ) -> IoResult<()>;
 }
 pub struct RequestPartProxySender {
@@ -32,9 +30,9 @@ impl RequestPartProxySender{
 impl ProxyHeadersSender<RequestLine> for RequestPartProxySender {
     fn send_first_line_and_headers(
         &self,
-        remote_host_stream: &mut TcpStream,
+        remote_host_stream: &mut dyn Write,
         request_line: RequestLine,
-        headers: &HashMap<String, String>,
+        headers: &HeaderMap,
     ) -> IoResult<()> {
         write_proxied_request_line(remote_host_stream, request_line, self.remote_host_name)?;
         write_proxied_headers(remote_host_stream, headers)
@@ -48,9 +46,9 @@ pub struct ResponsePartProxySender {
 impl ProxyHeadersSender<ResponseLine> for ResponsePartProxySender {
     fn send_first_line_and_headers(
         &self,
-        remote_host_stream: &mut TcpStream,
+        remote_host_stream: &mut dyn Write,
         first_line: ResponseLine,
-        headers: &HashMap<String, String>,
+        headers: &HeaderMap,
     ) -> IoResult<()> {
         write_proxied_response_status_line(remote_host_stream, first_line)?;
         write_proxied_headers(remote_host_stream, headers)
@@ -66,7 +64,7 @@ pub struct ProxyParser<'a, P: FirstLineParser, S: ProxyHeadersSender<P::HttpType
     current_position: usize,
     data: Vec<u8>,
     parsing_state: ParsingState,
-    remote_host_stream: &'a mut TcpStream,
+    remote_host_stream: &'a mut dyn Write,
     proxy_headers_sender: S,
 }
 impl<'a, P: FirstLineParser, S: ProxyHeadersSender<P::HttpType>>
@@ -74,7 +72,7 @@ impl<'a, P: FirstLineParser, S: ProxyHeadersSender<P::HttpType>>
 {
     pub fn new<'b>(
         first_line_parser: P,
-        remote_host_stream: &'b mut TcpStream,
+        remote_host_stream: &'b mut dyn Write,
         proxy_headers_sender: S,
     ) -> ProxyParser<'b, P, S> {
         ProxyParser {
@@ -154,6 +152,7 @@ impl<'a, P: FirstLineParser, S: ProxyHeadersSender<P::HttpType>>
                         Err(err) => match err {
                             HeaderParseError::HeadersDone => {
                                 self.current_position += 2;
+                                self.header_parser.check_message_framing()?;
                                 let content_length = match self
                                     .header_parser
                                     .header("content-length")
@@ -303,7 +302,7 @@ impl<'a, P: FirstLineParser, S: ProxyHeadersSender<P::HttpType>>
     }
 }
 
-pub fn write_proxied_request_line<T: Write>(
+pub fn write_proxied_request_line<T: Write + ?Sized>(
     stream_writer: &mut T,
     request: RequestLine,
     remote_host: &str,
@@ -318,18 +317,18 @@ pub fn write_proxied_request_line<T: Write>(
     Ok(())
 }
 
-pub fn write_proxied_headers<T: Write>(
+pub fn write_proxied_headers<T: Write + ?Sized>(
     stream_writer: &mut T,
-    headers: &HashMap<String, String>,
+    headers: &HeaderMap,
 ) -> IoResult<()> {
     let mut headers_response = String::new();
-    for (key, value) in headers {
-        if key == "host" {
+    for (key, value) in headers.iter() {
+        if key.eq_ignore_ascii_case("host") {
             continue;
         }
-        headers_response.push_str(key.as_str());
+        headers_response.push_str(key);
         headers_response.push_str(": ");
-        headers_response.push_str(value.as_str());
+        headers_response.push_str(value);
         headers_response.push_str("\r\n");
     }
     headers_response.push_str("\r\n");
@@ -338,7 +337,7 @@ pub fn write_proxied_headers<T: Write>(
 }
 
 
-pub fn write_proxied_response_status_line<T: Write>(
+pub fn write_proxied_response_status_line<T: Write + ?Sized>(
     stream_writer: &mut T,
     response: ResponseLine,
 ) -> IoResult<()> {
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, io::{Result as IoResult, Write}};
+use std::{collections::HashMap, convert::Infallible, io::{Result as IoResult, Write}};
 
-use crate::{extractor::{BodyContentError, Form, Json, RoutingError}, parser::first_line_parser::ResponseLine};
+use crate::{extensions::Extensions, extractor::{BodyContentError, Form, Json, QueryError, RoutingError, StateError}, headers::{HeaderMap, TypedHeaderError}, multipart::MultipartError, problem::Problem, session::SessionError, parser::first_line_parser::ResponseLine};
 
 
 use std::io;
@@ -12,25 +12,49 @@ use serde::Serialize;
 pub struct Response{
     status_message:StatusMessage,
     status_code:StatusCode,
-    headers:HashMap<String,String>,
-    body:Vec<u8>
+    headers:HeaderMap,
+    body:Vec<u8>,
+    // values that travel with the response without being sent, e.g. the Problem of a rejection
+    extensions:Extensions,
 }
 
 impl Response{
-    pub fn new(status_message:StatusMessage,status_code:StatusCode,headers:HashMap<String,String>,body:Vec<u8>)->Self{
-        Self { status_message, status_code, headers, body}
+    pub fn new(status_message:StatusMessage,status_code:StatusCode,headers:HeaderMap,body:Vec<u8>)->Self{
+        Self { status_message, status_code, headers, body, extensions: Extensions::default()}
 
 
     }
-    pub fn headers(&self)->&HashMap<String,String>{
+    pub fn headers(&self)->&HeaderMap{
         &self.headers
     }
+    pub fn headers_mut(&mut self)->&mut HeaderMap{
+        &mut self.headers
+    }
     pub fn body(&self)->&[u8]{
         &self.body
     }
     pub fn status_code(&self)->&StatusCode{
         &self.status_code
     }
+    pub fn status_message(&self)->&StatusMessage{
+        &self.status_message
+    }
+    pub fn set_status_code(&mut self,status_code:StatusCode){
+        self.status_message=status_code.status_message();
+        self.status_code=status_code;
+    }
+    pub fn set_header(&mut self,key:&str,value:&str){
+        self.headers.insert(key, value);
+    }
+    pub fn append_header(&mut self,key:&str,value:&str){
+        self.headers.append(key, value);
+    }
+    pub fn extensions(&self)->&Extensions{
+        &self.extensions
+    }
+    pub fn extensions_mut(&mut self)->&mut Extensions{
+        &mut self.extensions
+    }
 }
 pub struct Html(String);
 impl Html{
@@ -57,14 +81,7 @@ impl IntoResponse for serde_json::Error {
 
 impl IntoResponse for serde_urlencoded::de::Error {
     fn into_response(self) -> Response {
-        let message = b"url encoded data error";
-        let headers = get_common_headers_with_content_type_header(message, ContentType::TextPlain);
-        Response::new(
-            StatusMessage::BadRequest,
-            StatusCode::BadRequest,
-            headers,
-            message.to_vec(),
-        )
+        Problem::new(StatusCode::BadRequest, self.to_string()).into_response()
     }
 }
 
@@ -97,55 +114,77 @@ impl IntoResponse for serde_urlencoded::ser::Error {
 
 impl IntoResponse for RoutingError {
     fn into_response(self) -> Response {
-        let message = b"Not Found or url encoded error";
-        let headers = get_common_headers_with_content_type_header(message, ContentType::TextPlain);
-        Response::new(
-            StatusMessage::BadRequest,
-            StatusCode::BadRequest,
-            headers,
-            message.to_vec(),
-        )
+        match self {
+            RoutingError::InvalidParams(err) => {
+                Problem::new(StatusCode::BadRequest, format!("invalid path parameters: {}", err.message))
+                    .with_members(&err)
+                    .into_response()
+            }
+            err => Problem::new(StatusCode::NotFound, err.to_string()).into_response(),
+        }
     }
 }
 
-impl IntoResponse for BodyContentError {
+impl IntoResponse for QueryError {
     fn into_response(self) -> Response {
-        match self {
-            BodyContentError::ContentTypeMisMatch => {
-                let message = b"content type mismatch";
-                let headers =
-                    get_common_headers_with_content_type_header(message, ContentType::TextPlain);
-                Response::new(
-                    StatusMessage::BadRequest,
-                    StatusCode::BadRequest,
-                    headers,
-                    message.to_vec(),
-                )
+        Problem::new(StatusCode::BadRequest, self.to_string())
+            .with_members(&self.0)
+            .into_response()
+    }
+}
+
+impl IntoResponse for Infallible {
+    fn into_response(self) -> Response {
+        match self {}
+    }
+}
+
+impl IntoResponse for TypedHeaderError {
+    fn into_response(self) -> Response {
+        Problem::new(StatusCode::BadRequest, self.to_string()).into_response()
+    }
+}
+
+impl IntoResponse for MultipartError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            MultipartError::PartTooLarge(_) | MultipartError::TotalTooLarge | MultipartError::TooManyParts => {
+                StatusCode::PayloadTooLarge
             }
-            BodyContentError::JsonSerializationError(_) => {
-                let message = b"json shape mismatch";
-
-                let headers =
-                    get_common_headers_with_content_type_header(message, ContentType::TextPlain);
-
-                Response::new(
-                    StatusMessage::BadRequest,
-                    StatusCode::BadRequest,
-                    headers,
-                    message.to_vec(),
-                )
+            MultipartError::ContentTypeMisMatch => StatusCode::UnsupportedMediaType,
+            MultipartError::Io(_) => StatusCode::InternalServerError,
+            _ => StatusCode::BadRequest,
+        };
+        Problem::new(status_code, self.to_string()).into_response()
+    }
+}
+
+impl IntoResponse for SessionError {
+    fn into_response(self) -> Response {
+        Problem::new(StatusCode::InternalServerError, "session is not available").into_response()
+    }
+}
+
+impl IntoResponse for StateError {
+    fn into_response(self) -> Response {
+        Problem::new(StatusCode::InternalServerError, "server state is not configured").into_response()
+    }
+}
+
+impl IntoResponse for BodyContentError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            BodyContentError::ContentTypeMisMatch | BodyContentError::UnsupportedCharset(_) => {
+                StatusCode::UnsupportedMediaType
             }
-            BodyContentError::UrlEncodedFormSerialization(_) => {
-                let message = b"bad form inputs";
-                let headers =
-                    get_common_headers_with_content_type_header(message, ContentType::TextPlain);
-                Response::new(
-                    StatusMessage::BadRequest,
-                    StatusCode::BadRequest,
-                    headers,
-                    message.to_vec(),
-                )
+            _ => StatusCode::BadRequest,
+        };
+        let problem = Problem::new(status_code, self.to_string());
+        match &self {
+            BodyContentError::JsonSerializationError(err) | BodyContentError::UrlEncodedFormSerialization(err) => {
+                problem.with_members(err).into_response()
             }
+            _ => problem.into_response(),
         }
     }
 }
@@ -285,16 +324,89 @@ pub enum StatusMessage {
 }
 
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum StatusCode {
     Ok,
     BadRequest,
     InternalServerError,
     NotFound,
-    MethodNotAllowed
+    MethodNotAllowed,
+    RequestTimeout,
+    PayloadTooLarge,
+    UriTooLong,
+    UnsupportedMediaType,
+    UnprocessableEntity,
+    RequestHeaderFieldsTooLarge,
+}
+
+impl StatusCode {
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            StatusCode::Ok => 200,
+            StatusCode::BadRequest => 400,
+            StatusCode::InternalServerError => 500,
+            StatusCode::NotFound => 404,
+            StatusCode::MethodNotAllowed => 405,
+            StatusCode::RequestTimeout => 408,
+            StatusCode::PayloadTooLarge => 413,
+            StatusCode::UriTooLong => 414,
+            StatusCode::UnsupportedMediaType => 415,
+            StatusCode::UnprocessableEntity => 422,
+            StatusCode::RequestHeaderFieldsTooLarge => 431,
+        }
+    }
+    pub fn reason_phrase(&self) -> &'static str {
+        match self {
+            StatusCode::Ok => "OK",
+            StatusCode::BadRequest => "Bad Request",
+            StatusCode::InternalServerError => "Internal Server Error",
+            StatusCode::NotFound => "Not Found",
+            StatusCode::MethodNotAllowed => "Method Not Allowed",
+            StatusCode::RequestTimeout => "Request Timeout",
+            StatusCode::PayloadTooLarge => "Content Too Large",
+            StatusCode::UriTooLong => "URI Too Long",
+            StatusCode::UnsupportedMediaType => "Unsupported Media Type",
+            StatusCode::UnprocessableEntity => "Unprocessable Content",
+            StatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+        }
+    }
+    pub fn status_message(&self) -> StatusMessage {
+        match self {
+            StatusCode::Ok => StatusMessage::Ok,
+            StatusCode::BadRequest => StatusMessage::BadRequest,
+            StatusCode::InternalServerError => StatusMessage::InternalServerError,
+            StatusCode::NotFound => StatusMessage::NotFound,
+            StatusCode::MethodNotAllowed => StatusMessage::MethodNotAllowed,
+            StatusCode::RequestTimeout => StatusMessage::RequestTimeout,
+            StatusCode::PayloadTooLarge => StatusMessage::PayloadTooLarge,
+            StatusCode::UriTooLong => StatusMessage::UriTooLong,
+            StatusCode::UnsupportedMediaType => StatusMessage::UnsupportedMediaType,
+            StatusCode::UnprocessableEntity => StatusMessage::UnprocessableEntity,
+            StatusCode::RequestHeaderFieldsTooLarge => StatusMessage::RequestHeaderFieldsTooLarge,
+        }
+    }
+    pub fn status_line(&self) -> &'static str {
+        match self {
+            StatusCode::Ok => "HTTP/1.1 200 OK\r\n",
+            StatusCode::BadRequest => "HTTP/1.1 400 Bad Request\r\n",
+            StatusCode::InternalServerError => "HTTP/1.1 500 Internal Server Error\r\n",
+            StatusCode::NotFound => "HTTP/1.1 404 Not Found\r\n",
+            StatusCode::MethodNotAllowed => "HTTP/1.1 405 Method Not Allowed\r\n",
+            StatusCode::RequestTimeout => "HTTP/1.1 408 Request Timeout\r\n",
+            StatusCode::PayloadTooLarge => "HTTP/1.1 413 Content Too Large\r\n",
+            StatusCode::UriTooLong => "HTTP/1.1 414 URI Too Long\r\n",
+            StatusCode::UnsupportedMediaType => "HTTP/1.1 415 Unsupported Media Type\r\n",
+            StatusCode::UnprocessableEntity => "HTTP/1.1 422 Unprocessable Content\r\n",
+            StatusCode::RequestHeaderFieldsTooLarge => {
+                "HTTP/1.1 431 Request Header Fields Too Large\r\n"
+            }
+        }
+    }
 }
 
 pub enum ContentType{
     ApplicationJson,
+    ApplicationProblemJson,
     ApplicationUrlEncoded,
     TextPlain,
     ImageJpeg,
@@ -302,27 +414,11 @@ pub enum ContentType{
 }
 
 pub fn write_status_line<T: Write>(stream_writer: &mut T, status: StatusCode) -> IoResult<()> {
-    let mut status = match status {
-        StatusCode::Ok => String::from("HTTP/1.1 200 OK"),
-        StatusCode::BadRequest => String::from("HTTP/1.1 400 Bad Request"),
-        StatusCode::InternalServerError => String::from("HTTP/1.1 500 Internal Server Error"),
-        StatusCode::NotFound=> String::from("HTTP/1.1 404 Not Found"),
-        StatusCode::MethodNotAllowed=>String::from("HTTP/1.1 405 Method Not Allowed")
-    };
-    status.push_str("\r\n");
-    stream_writer.write_all(status.as_bytes())?;
+    stream_writer.write_all(status.status_line().as_bytes())?;
     Ok(())
 }
 pub fn write_response_status_line<T: Write>(stream_writer: &mut T, status: &StatusCode) -> IoResult<()> {
-    let mut status = match status {
-        StatusCode::Ok => String::from("HTTP/1.1 200 OK"),
-        StatusCode::BadRequest => String::from("HTTP/1.1 400 Bad Request"),
-        StatusCode::InternalServerError => String::from("HTTP/1.1 500 Internal Server Error"),
-        StatusCode::NotFound=> String::from("HTTP/1.1 404 Not Found"),
-        StatusCode::MethodNotAllowed=>String::from("HTTP/1.1 405 Method Not Allowed")
-    };
-    status.push_str("\r\n");
-    stream_writer.write_all(status.as_bytes())?;
+    stream_writer.write_all(status.status_line().as_bytes())?;
     Ok(())
 }
 
@@ -348,7 +444,6 @@ pub fn get_preflight_headers() -> HashMap<&'static str, &'static str> {
         ("Access-Control-Allow-Origin", "https://hoppscotch.io"),
         ("Access-Control-Allow-Methods", "*"),
         ("Access-Control-Allow-Headers", "*"),
-        ("Connection", "close"),
     ])
 }
 
@@ -356,29 +451,32 @@ pub fn get_common_headers() -> HashMap<&'static str, &'static str> {
     HashMap::from([
         ("Access-Control-Allow-Origin", "https://hoppscotch.io"),
         ("Content-Length","0"),
-        ("Connection", "close"),
     ])
 }
 
 
 
-pub fn get_common_headers_with_content_type_header(body:&[u8],content_type:ContentType) -> HashMap<String, String> {
+pub fn get_common_headers_with_content_type_header(body:&[u8],content_type:ContentType) -> HeaderMap {
     let content_type=match content_type{
         ContentType::ApplicationJson => "application/json",
+        ContentType::ApplicationProblemJson => "application/problem+json",
         ContentType::ApplicationUrlEncoded => "application/x-www-form-urlencoded",
         ContentType::TextPlain => "text/plain",
         ContentType::ImageJpeg => "image/jpeg",
         ContentType::TextHtml => "text/html",
     };
     let body_length=body.len();
-    HashMap::from([
-        ("Access-Control-Allow-Origin".to_string(), "https://hoppscotch.io".to_string()),
-        ("Content-Length".to_string(),body_length.to_string()),
-        ("Connection".to_string(), "close".to_string()),
-        ("Content-Type".to_string(), content_type.to_string()),
+    HeaderMap::from_iter([
+        ("Access-Control-Allow-Origin", "https://hoppscotch.io".to_string()),
+        ("Content-Length",body_length.to_string()),
+        ("Content-Type", content_type.to_string()),
     ])
 }
 
+pub fn connection_header_value(keep_alive: bool) -> &'static str {
+    if keep_alive { "keep-alive" } else { "close" }
+}
+
 pub fn write_headers<T: Write>(
     stream_writer: &mut T,
     headers: HashMap<&str, &str>,
@@ -397,10 +495,10 @@ pub fn write_headers<T: Write>(
 
 pub fn write_response_headers<T: Write>(
     stream_writer: &mut T,
-    headers: &HashMap<String, String>,
+    headers: &HeaderMap,
 ) -> IoResult<()> {
     let mut headers_response = String::new();
-    for (key, value) in headers {
+    for (key, value) in headers.iter() {
         headers_response.push_str(key);
         headers_response.push_str(": ");
         headers_response.push_str(value);
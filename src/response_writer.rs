@@ -1,24 +1,18 @@
 use std::{collections::HashMap, io::{Write,Result as IoResult}, net::TcpStream};
 
-use crate::response::{get_common_headers, ContentType, StatusCode};
+use crate::response::{connection_header_value, get_common_headers, ContentType, StatusCode};
 
 
-pub struct ResponseWriter<'a> {
-    connection: &'a mut TcpStream,
+pub struct ResponseWriter<'a, W: Write = TcpStream> {
+    connection: &'a mut W,
 }
 
-impl<'a> ResponseWriter<'a> {
-    pub fn new<'b>(connection:&'b mut TcpStream)->ResponseWriter<'b>{
+impl<'a, W: Write> ResponseWriter<'a, W> {
+    pub fn new<'b>(connection:&'b mut W)->ResponseWriter<'b, W>{
         ResponseWriter { connection}
     }
-    pub fn write_status_line(self, status_code: StatusCode) -> IoResult<Headers<'a>> {
-        let status = match status_code {
-            StatusCode::Ok => "HTTP/1.1 200 OK\r\n",
-            StatusCode::BadRequest => "HTTP/1.1 400 Bad Request\r\n",
-            StatusCode::InternalServerError => "HTTP/1.1 500 Internal Server Error\r\n",
-            StatusCode::NotFound=> "HTTP/1.1 404 Not Found",
-            StatusCode::MethodNotAllowed=>"HTTP/1.1 405 Method Not Allowed"
-        };
+    pub fn write_status_line(self, status_code: StatusCode) -> IoResult<Headers<'a, W>> {
+        let status = status_code.status_line();
         self.connection.write_all(status.as_bytes())?;
         Ok(Headers {
             connection:self.connection
@@ -26,20 +20,13 @@ impl<'a> ResponseWriter<'a> {
     }
 }
 
-pub struct Headers<'a> {
-    connection: &'a mut TcpStream,
+pub struct Headers<'a, W: Write = TcpStream> {
+    connection: &'a mut W,
 }
 
-impl<'a> Headers<'a> {
-    pub fn write_default_headers(self,content_type:ContentType) -> IoResult<Body<'a>>{
-        let headers=get_common_headers();
-        let mut headers_response = String::new();
-        for (key, value) in headers {
-            headers_response.push_str(key);
-            headers_response.push_str(": ");
-            headers_response.push_str(value);
-            headers_response.push_str("\r\n");
-        }
+impl<'a, W: Write> Headers<'a, W> {
+    pub fn write_default_headers(self,content_type:ContentType) -> IoResult<Body<'a, W>>{
+        let headers_response = format_headers(HashMap::new());
         self.connection.write_all(content_type.as_bytes())?;
         self.connection.write_all(headers_response.as_bytes())?;
         Ok(Body {
@@ -47,22 +34,8 @@ impl<'a> Headers<'a> {
             transfer_encoding_header_written:false
         })
     }
-    pub fn write_headers(self,custom_headers:HashMap<&str,&str>,content_type:ContentType)->IoResult<Body<'a>>{
-        let mut headers_response = String::new();
-        let mut headers=get_common_headers();
-        for (key, value) in custom_headers {
-            let lower_key=key.to_lowercase();
-            if lower_key=="content-type" || lower_key=="content-length" || lower_key=="connection"{
-                continue;
-            }
-            headers.insert(key, value);
-        }
-        for (key, value) in headers {
-            headers_response.push_str(key);
-            headers_response.push_str(": ");
-            headers_response.push_str(value);
-            headers_response.push_str("\r\n");
-        }
+    pub fn write_headers(self,custom_headers:HashMap<&str,&str>,content_type:ContentType)->IoResult<Body<'a, W>>{
+        let headers_response = format_headers(custom_headers);
         self.connection.write_all(content_type.as_bytes())?;
         self.connection.write_all(headers_response.as_bytes())?;
         Ok(Body {
@@ -71,22 +44,8 @@ impl<'a> Headers<'a> {
         })
 
     }
-    pub fn write_headers_with_trailer_headers(self,custom_headers:HashMap<&str,&str>,trailer_headers_keys:Vec<&str>,content_type:ContentType)->IoResult<ChunkedBodyWithTrailerHeaders<'a>>{
-        let mut headers_response = String::new();
-        let mut headers=get_common_headers();
-        for (key, value) in custom_headers {
-            let lower_key=key.to_lowercase();
-            if lower_key=="content-type" || lower_key=="content-length" || lower_key=="connection"{
-                continue;
-            }
-            headers.insert(key, value);
-        }
-        for (key, value) in headers {
-            headers_response.push_str(key);
-            headers_response.push_str(": ");
-            headers_response.push_str(value);
-            headers_response.push_str("\r\n");
-        }
+    pub fn write_headers_with_trailer_headers(self,custom_headers:HashMap<&str,&str>,trailer_headers_keys:Vec<&str>,content_type:ContentType)->IoResult<ChunkedBodyWithTrailerHeaders<'a, W>>{
+        let mut headers_response = format_headers(custom_headers);
         self.connection.write_all(content_type.as_bytes())?;
         let trailer_headers=format!("Trailer: {}\r\nTransfer-Encoding: chunked\r\n\r\n",trailer_headers_keys.join(""));
         headers_response.push_str(&trailer_headers);
@@ -97,12 +56,12 @@ impl<'a> Headers<'a> {
 
     }
 }
-pub struct Body<'a> {
-    connection: &'a mut TcpStream,
+pub struct Body<'a, W: Write = TcpStream> {
+    connection: &'a mut W,
     transfer_encoding_header_written:bool
 }
 
-impl<'a> Body<'a> {
+impl<W: Write> Body<'_, W> {
     pub fn write_body_plain_text(self,body:&str)->IoResult<ManualResponse >{
         let mut body_bytes=Vec::<u8>::new();
         let content_length_header=format!("Content-Length: {}\r\n\r\n",body.len());
@@ -142,8 +101,8 @@ impl<'a> Body<'a> {
 }
 
 
-pub struct ChunkedBodyWithTrailerHeaders<'a> {
-    connection: &'a mut TcpStream
+pub struct ChunkedBodyWithTrailerHeaders<'a, W: Write = TcpStream> {
+    connection: &'a mut W
 }
 
 
@@ -152,6 +111,7 @@ impl ContentType {
     fn as_bytes(&self) -> &[u8] {
         match self {
             ContentType::ApplicationJson => b"Content-Type: application/json; charset=utf-8\r\n",
+            ContentType::ApplicationProblemJson => b"Content-Type: application/problem+json\r\n",
             ContentType::ImageJpeg => b"Content-Type: image/jpeg\r\n",
             ContentType::TextHtml => b"Content-Type: text/html; charset=utf-8\r\n",
             ContentType::TextPlain => b"Content-Type: text/plain; charset=utf-8\r\n",
@@ -159,7 +119,7 @@ impl ContentType {
         }
     }
 }
-impl<'a> ChunkedBodyWithTrailerHeaders<'a> {
+impl<W: Write> ChunkedBodyWithTrailerHeaders<'_, W> {
     pub fn write_chunk(&mut self,chunk:&[u8])->IoResult<()>{
         let hex_string_upper = format!("{:X}\r\n", chunk.len());
         self.connection.write_all(hex_string_upper.as_bytes())?;
@@ -190,3 +150,25 @@ impl<'a> ChunkedBodyWithTrailerHeaders<'a> {
 
 pub struct ManualResponse{
 }
+
+// responses written by hand are the last ones sent on the connection
+fn format_headers(custom_headers:HashMap<&str,&str>)->String{
+    let mut headers_response = String::new();
+    let mut headers=get_common_headers();
+    headers.remove("Content-Length");
+    for (key, value) in custom_headers {
+        let lower_key=key.to_lowercase();
+        if lower_key=="content-type" || lower_key=="content-length" || lower_key=="connection"{
+            continue;
+        }
+        headers.insert(key, value);
+    }
+    headers.insert("Connection", connection_header_value(false));
+    for (key, value) in headers {
+        headers_response.push_str(key);
+        headers_response.push_str(": ");
+        headers_response.push_str(value);
+        headers_response.push_str("\r\n");
+    }
+    headers_response
+}
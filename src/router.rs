@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use crate::{
+    handler::{Handler, HandlerFunction, Service},
+    parser::http_message_parser::Request,
+    response::Response,
+    routing::HttpVerb,
+};
+
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, request: Request, next: &dyn Service) -> Response;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Request, &dyn Service) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, request: Request, next: &dyn Service) -> Response {
+        self(request, next)
+    }
+}
+
+struct Layered {
+    middleware: Arc<dyn Middleware>,
+    inner: Box<dyn Service>,
+}
+
+impl Service for Layered {
+    fn call(&self, request: Request) -> Response {
+        self.middleware.handle(request, self.inner.as_ref())
+    }
+
+    fn clone_box(&self) -> Box<dyn Service> {
+        Box::new(Layered {
+            middleware: Arc::clone(&self.middleware),
+            inner: self.inner.clone_box(),
+        })
+    }
+
+    fn streams_body(&self) -> bool {
+        self.inner.streams_body()
+    }
+}
+
+pub struct Route {
+    // None means the route answers every method
+    pub method: Option<HttpVerb>,
+    pub path: String,
+    pub service: Box<dyn Service>,
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn route<Args, F>(mut self, method: HttpVerb, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method: Some(method),
+            path: path.into(),
+            service: Box::new(Handler::new(handler)),
+        });
+        self
+    }
+    pub fn any<Args, F>(mut self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method: None,
+            path: path.into(),
+            service: Box::new(Handler::new(handler)),
+        });
+        self
+    }
+    pub fn get<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::GET, path, handler)
+    }
+    pub fn post<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::POST, path, handler)
+    }
+    pub fn put<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::PUT, path, handler)
+    }
+    pub fn patch<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::PATCH, path, handler)
+    }
+    pub fn delete<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::DELETE, path, handler)
+    }
+    pub fn head<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::HEAD, path, handler)
+    }
+    pub fn options<Args, F>(self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.route(HttpVerb::OPTIONS, path, handler)
+    }
+    // wraps every route added so far, routes added afterwards are left alone
+    pub fn layer<M: Middleware>(mut self, middleware: M) -> Self {
+        let middleware: Arc<dyn Middleware> = Arc::new(middleware);
+        self.routes = self
+            .routes
+            .into_iter()
+            .map(|route| Route {
+                service: Box::new(Layered {
+                    middleware: Arc::clone(&middleware),
+                    inner: route.service,
+                }),
+                ..route
+            })
+            .collect();
+        self
+    }
+    pub fn nest(mut self, prefix: &str, router: Router) -> Self {
+        for mut route in router.routes {
+            route.path = join_paths(prefix, &route.path);
+            self.routes.push(route);
+        }
+        self
+    }
+    pub fn merge(mut self, router: Router) -> Self {
+        self.routes.extend(router.routes);
+        self
+    }
+    pub fn into_routes(self) -> Vec<Route> {
+        self.routes
+    }
+}
+
+pub fn join_paths(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match path.trim_start_matches('/') {
+        "" if prefix.is_empty() => "/".to_string(),
+        "" => prefix.to_string(),
+        path => format!("{}/{}", prefix, path),
+    }
+}
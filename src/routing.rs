@@ -1,69 +1,229 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use matchit::Router;
+use matchit::{Match, Router};
 
-use crate::handler::{Handler, HandlerFunction, Service};
+use crate::{
+    extensions::Extensions,
+    handler::{Handler, HandlerFunction, Service},
+    problem::Problem,
+    response::{IntoResponse, Response, StatusCode},
+    router::{self, join_paths},
+};
 
 
-#[derive(Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum HttpVerb {
     GET,
+    HEAD,
     POST,
     PUT,
     PATCH,
     DELETE,
     OPTIONS,
-    HEAD,
+    Custom(String),
+}
+
+impl HttpVerb {
+    pub fn as_str(&self) -> &str {
+        match self {
+            HttpVerb::GET => "GET",
+            HttpVerb::POST => "POST",
+            HttpVerb::PUT => "PUT",
+            HttpVerb::PATCH => "PATCH",
+            HttpVerb::DELETE => "DELETE",
+            HttpVerb::OPTIONS => "OPTIONS",
+            HttpVerb::HEAD => "HEAD",
+            HttpVerb::Custom(method) => method,
+        }
+    }
+}
+
+impl From<&str> for HttpVerb {
+    fn from(method: &str) -> Self {
+        match method {
+            "GET" => HttpVerb::GET,
+            "POST" => HttpVerb::POST,
+            "PUT" => HttpVerb::PUT,
+            "PATCH" => HttpVerb::PATCH,
+            "DELETE" => HttpVerb::DELETE,
+            "OPTIONS" => HttpVerb::OPTIONS,
+            "HEAD" => HttpVerb::HEAD,
+            _ => HttpVerb::Custom(method.to_string()),
+        }
+    }
 }
 
 
 
+pub type ErrorRenderer = Box<dyn Fn(&str) -> Response + Send + Sync>;
+pub type RejectionRenderer = Box<dyn Fn(&Problem) -> Response + Send + Sync>;
+
 #[derive(Default)]
-pub struct RoutingMap(HashMap<HttpVerb, Router<Box<dyn Service>>>);
+pub struct RoutingMap {
+    method_routers: HashMap<HttpVerb, Router<Box<dyn Service>>>,
+    any_router: Router<Box<dyn Service>>,
+    fallback: Option<Box<dyn Service>>,
+    error_pages: HashMap<StatusCode, ErrorRenderer>,
+    rejection_renderer: Option<RejectionRenderer>,
+    state: Extensions,
+}
 
 impl RoutingMap {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
     pub fn add_handler<Args,F>(
         &mut self,
         http_verb: HttpVerb,
         handler: F,
-        route: &'static str,
+        route: impl Into<String>,
     ) -> Result<(), matchit::InsertError>
     where
     F: HandlerFunction<Args>,
     Args:Send + Sync +'static
     {
-        let router = self.0.entry(http_verb).or_default();
+        let router = self.method_routers.entry(http_verb).or_default();
         router.insert(route, Box::new(Handler::new(handler)))?;
         Ok(())
     }
+    // handlers added here answer every method that has no route of its own for the path
+    pub fn add_any_handler<Args,F>(
+        &mut self,
+        handler: F,
+        route: impl Into<String>,
+    ) -> Result<(), matchit::InsertError>
+    where
+    F: HandlerFunction<Args>,
+    Args:Send + Sync +'static
+    {
+        self.any_router.insert(route, Box::new(Handler::new(handler)))?;
+        Ok(())
+    }
+    pub fn add_service(
+        &mut self,
+        http_verb: Option<HttpVerb>,
+        route: impl Into<String>,
+        service: Box<dyn Service>,
+    ) -> Result<(), matchit::InsertError> {
+        match http_verb {
+            Some(http_verb) => self.method_routers.entry(http_verb).or_default().insert(route, service),
+            None => self.any_router.insert(route, service),
+        }
+    }
+    // mounts every route of the router under the prefix, params in the prefix are
+    // extracted by Path like any other
+    pub fn nest(&mut self, prefix: &str, router: router::Router) -> Result<(), matchit::InsertError> {
+        for route in router.into_routes() {
+            self.add_service(route.method, join_paths(prefix, &route.path), route.service)?;
+        }
+        Ok(())
+    }
+    pub fn merge(&mut self, router: router::Router) -> Result<(), matchit::InsertError> {
+        for route in router.into_routes() {
+            self.add_service(route.method, route.path, route.service)?;
+        }
+        Ok(())
+    }
+    pub fn find_route<'r, 'p>(&'r self, http_verb: &HttpVerb, route: &'p str) -> Option<Match<'r, 'p, &'r dyn Service>> {
+        let method_route = |http_verb: &HttpVerb| {
+            self.method_routers
+                .get(http_verb)
+                .and_then(|method_router| method_router.at(route).ok())
+        };
+        // HEAD is answered by the GET handler unless it has a route of its own
+        let matched_route = method_route(http_verb)
+            .or_else(|| match http_verb {
+                HttpVerb::HEAD => method_route(&HttpVerb::GET),
+                _ => None,
+            })
+            .or_else(|| self.any_router.at(route).ok())?;
+        Some(Match {
+            value: matched_route.value.as_ref(),
+            params: matched_route.params,
+        })
+    }
+    // every method the path can be served with, ordered so the Allow header is stable
+    pub fn allowed_methods(&self, route: &str) -> Vec<HttpVerb> {
+        let mut allowed_methods = if self.any_router.at(route).is_ok() {
+            vec![HttpVerb::GET, HttpVerb::POST, HttpVerb::PUT, HttpVerb::PATCH, HttpVerb::DELETE, HttpVerb::HEAD]
+        } else {
+            Vec::new()
+        };
+        for (http_verb, method_router) in &self.method_routers {
+            if method_router.at(route).is_ok() && !allowed_methods.contains(http_verb) {
+                allowed_methods.push(http_verb.clone());
+            }
+        }
+        if allowed_methods.contains(&HttpVerb::GET) && !allowed_methods.contains(&HttpVerb::HEAD) {
+            allowed_methods.push(HttpVerb::HEAD);
+        }
+        if !allowed_methods.is_empty() && !allowed_methods.contains(&HttpVerb::OPTIONS) {
+            allowed_methods.push(HttpVerb::OPTIONS);
+        }
+        allowed_methods.sort();
+        allowed_methods
+    }
     pub fn get_handler(&self,http_verb:&HttpVerb,route:&str) ->Option<&dyn Service> 
     
     {
-        let method_route=self.0.get(http_verb)?;
-        let matched_route = method_route.at(route).ok()?;
-        let handler_function=matched_route.value;
-        Some(handler_function.as_ref())
+        let matched_route = self.find_route(http_verb, route)?;
+        Some(matched_route.value)
+    }
+    // whether the service answering this request reads the body off the connection itself
+    pub fn streams_body(&self, http_verb: &HttpVerb, route: &str) -> bool {
+        self.get_handler(http_verb, route)
+            .or(self.fallback())
+            .is_some_and(|service| service.streams_body())
+    }
+    pub fn set_fallback<Args,F>(&mut self, handler: F)
+    where
+    F: HandlerFunction<Args>,
+    Args:Send + Sync +'static
+    {
+        self.fallback = Some(Box::new(Handler::new(handler)));
+    }
+    pub fn fallback(&self) -> Option<&dyn Service> {
+        self.fallback.as_deref()
+    }
+    // the renderer gets a short description of the error, the status code is always
+    // forced to the one being rendered
+    pub fn set_error_page<F, R>(&mut self, status_code: StatusCode, renderer: F)
+    where
+    F: Fn(&str) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+    {
+        self.error_pages.insert(status_code, Box::new(move |message| renderer(message).into_response()));
+    }
+    pub fn render_error(&self, status_code: StatusCode, message: &str) -> Option<Response> {
+        let renderer = self.error_pages.get(&status_code)?;
+        let mut response = renderer(message);
+        response.set_status_code(status_code);
+        Some(response)
+    }
+    // unlike error pages the renderer picks the status, a 400 may become a 422
+    pub fn set_rejection_renderer<F, R>(&mut self, renderer: F)
+    where
+    F: Fn(&Problem) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+    {
+        self.rejection_renderer = Some(Box::new(move |problem| renderer(problem).into_response()));
+    }
+    // responses without a Problem are not rejections and pass through untouched
+    pub fn render_rejection(&self, response: Response) -> Response {
+        match (&self.rejection_renderer, response.extensions().get::<Problem>()) {
+            (Some(renderer), Some(problem)) => renderer(&problem),
+            _ => response,
+        }
+    }
+    pub fn state_mut(&mut self) -> &mut Extensions {
+        &mut self.state
+    }
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.state.get::<T>()
     }
     pub fn get_method_router(&self,http_verb: &HttpVerb)->Option<&Router<Box<dyn Service>>>
     {
-        self.0.get(http_verb)
+        self.method_routers.get(http_verb)
 
     }
 }
-
-
-
-
-
-
-
-
-
-
-
-
-
-
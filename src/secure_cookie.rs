@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, Generate, KeyInit, Payload},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, KeyInit as MacKeyInit, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::{
+    cookie::{Cookie, CookieJar},
+    extractor::{FromRequest, StateError},
+    parser::http_message_parser::Request,
+    response::{IntoResponse, Response},
+};
+
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl Key {
+    // the first half of the master key signs, the second half encrypts
+    pub fn from_bytes(master: &[u8]) -> Result<Self, KeyError> {
+        if master.len() < 64 {
+            return Err(KeyError::TooShort(master.len()));
+        }
+        let mut signing = [0; 32];
+        let mut encryption = [0; 32];
+        signing.copy_from_slice(&master[..32]);
+        encryption.copy_from_slice(&master[32..64]);
+        Ok(Self {
+            signing,
+            encryption,
+        })
+    }
+    pub fn generate() -> Self {
+        let signing = aes_gcm::Key::<Aes256Gcm>::generate();
+        let encryption = aes_gcm::Key::<Aes256Gcm>::generate();
+        Self {
+            signing: signing.into(),
+            encryption: encryption.into(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum KeyError {
+    #[error("a cookie key needs at least 64 bytes, got {0}")]
+    TooShort(usize),
+}
+
+// the current key signs and encrypts new cookies, previous keys are only used to read
+// cookies issued before a rotation
+#[derive(Clone)]
+pub struct Keys {
+    current: Key,
+    previous: Vec<Key>,
+}
+
+impl Keys {
+    pub fn new(current: Key) -> Self {
+        Self {
+            current,
+            previous: Vec::new(),
+        }
+    }
+    pub fn with_previous(mut self, key: Key) -> Self {
+        self.previous.push(key);
+        self
+    }
+    // the current key comes first, a match at any later position means a rotated key
+    fn all(&self) -> impl Iterator<Item = &Key> {
+        std::iter::once(&self.current).chain(&self.previous)
+    }
+}
+
+// the cookie name with its length in front, names may contain `=` once percent-decoded so
+// `a` + `b=c` and `a=b` + `c` must not produce the same input
+fn bound_name(name: &str) -> Vec<u8> {
+    let mut bound = (name.len() as u64).to_be_bytes().to_vec();
+    bound.extend_from_slice(name.as_bytes());
+    bound
+}
+
+fn signer(key: &Key, name: &str) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as MacKeyInit>::new_from_slice(&key.signing)
+        .expect("hmac accepts keys of any length");
+    // the name is part of the signature so a value can't be replayed under another cookie
+    mac.update(&bound_name(name));
+    mac
+}
+
+fn sign(key: &Key, name: &str, value: &str) -> String {
+    let mut mac = signer(key, name);
+    mac.update(value.as_bytes());
+    format!("{}.{}", URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()), value)
+}
+
+// the value and whether it was signed with the current key
+fn verify(keys: &Keys, name: &str, signed_value: &str) -> Option<(String, bool)> {
+    let (signature, value) = signed_value.split_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    keys.all().enumerate().find_map(|(index, key)| {
+        let mut mac = signer(key, name);
+        mac.update(value.as_bytes());
+        mac.verify_slice(&signature).ok().map(|_| (value.to_string(), index == 0))
+    })
+}
+
+fn encrypt(key: &Key, name: &str, value: &str) -> String {
+    let cipher = Aes256Gcm::new(&key.encryption.into());
+    let nonce = Nonce::generate();
+    let aad = bound_name(name);
+    let payload = Payload {
+        msg: value.as_bytes(),
+        aad: &aad,
+    };
+    let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .expect("encrypting a cookie value can't fail");
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    URL_SAFE_NO_PAD.encode(sealed)
+}
+
+// the value and whether it was encrypted with the current key
+fn decrypt(keys: &Keys, name: &str, sealed_value: &str) -> Option<(String, bool)> {
+    let sealed = URL_SAFE_NO_PAD.decode(sealed_value).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_from(nonce).ok()?;
+    let aad = bound_name(name);
+    keys.all().enumerate().find_map(|(index, key)| {
+        let cipher = Aes256Gcm::new(&key.encryption.into());
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &aad,
+        };
+        let plaintext = cipher.decrypt(&nonce, payload).ok()?;
+        Some((String::from_utf8(plaintext).ok()?, index == 0))
+    })
+}
+
+fn keys_from_state(request: &Request) -> Result<Arc<Keys>, StateError> {
+    request
+        .routing()
+        .state::<Keys>()
+        .ok_or(StateError::Missing(std::any::type_name::<Keys>()))
+}
+
+// cookies whose values carry an HMAC-SHA256 signature, readable but not forgeable by the client
+#[derive(Clone)]
+pub struct SignedCookieJar {
+    jar: CookieJar,
+    keys: Arc<Keys>,
+}
+
+impl SignedCookieJar {
+    // cookies still signed with a previous key are signed again with the current one,
+    // re-issued with Path=/ since the request doesn't say which attributes they had
+    pub fn new(jar: CookieJar, keys: Arc<Keys>) -> Self {
+        let rotated: Vec<Cookie> = jar
+            .iter()
+            .filter_map(|cookie| match verify(&keys, cookie.name(), cookie.value())? {
+                (value, false) => Some(Cookie::new(cookie.name(), value).path("/")),
+                (_, true) => None,
+            })
+            .collect();
+        let mut signed_jar = Self { jar, keys };
+        for cookie in rotated {
+            signed_jar.add(cookie);
+        }
+        signed_jar
+    }
+    // cookies with a missing or wrong signature are treated as absent
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        let mut cookie = self.jar.get(name)?.clone();
+        let (value, _) = verify(&self.keys, name, cookie.value())?;
+        cookie.set_value(value);
+        Some(cookie)
+    }
+    pub fn add(&mut self, mut cookie: Cookie) {
+        let signed_value = sign(&self.keys.current, cookie.name(), cookie.value());
+        cookie.set_value(signed_value);
+        self.jar.add(cookie);
+    }
+    pub fn remove(&mut self, cookie: impl Into<Cookie>) {
+        self.jar.remove(cookie);
+    }
+    pub fn into_jar(self) -> CookieJar {
+        self.jar
+    }
+}
+
+impl FromRequest for SignedCookieJar {
+    type Error = StateError;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        let keys = keys_from_state(request)?;
+        Ok(SignedCookieJar::new(CookieJar::from_request_headers(request), keys))
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (SignedCookieJar, R) {
+    fn into_response(self) -> Response {
+        (self.0.jar, self.1).into_response()
+    }
+}
+
+// cookies encrypted with AES-256-GCM, the client can neither read nor alter them
+#[derive(Clone)]
+pub struct PrivateCookieJar {
+    jar: CookieJar,
+    keys: Arc<Keys>,
+}
+
+impl PrivateCookieJar {
+    // like SignedCookieJar::new, cookies sealed with a previous key are sealed again
+    pub fn new(jar: CookieJar, keys: Arc<Keys>) -> Self {
+        let rotated: Vec<Cookie> = jar
+            .iter()
+            .filter_map(|cookie| match decrypt(&keys, cookie.name(), cookie.value())? {
+                (value, false) => Some(Cookie::new(cookie.name(), value).path("/")),
+                (_, true) => None,
+            })
+            .collect();
+        let mut private_jar = Self { jar, keys };
+        for cookie in rotated {
+            private_jar.add(cookie);
+        }
+        private_jar
+    }
+    // cookies that fail to decrypt are treated as absent
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        let mut cookie = self.jar.get(name)?.clone();
+        let (value, _) = decrypt(&self.keys, name, cookie.value())?;
+        cookie.set_value(value);
+        Some(cookie)
+    }
+    pub fn add(&mut self, mut cookie: Cookie) {
+        let sealed_value = encrypt(&self.keys.current, cookie.name(), cookie.value());
+        cookie.set_value(sealed_value);
+        self.jar.add(cookie);
+    }
+    pub fn remove(&mut self, cookie: impl Into<Cookie>) {
+        self.jar.remove(cookie);
+    }
+    pub fn into_jar(self) -> CookieJar {
+        self.jar
+    }
+}
+
+impl FromRequest for PrivateCookieJar {
+    type Error = StateError;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        let keys = keys_from_state(request)?;
+        Ok(PrivateCookieJar::new(CookieJar::from_request_headers(request), keys))
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (PrivateCookieJar, R) {
+    fn into_response(self) -> Response {
+        (self.0.jar, self.1).into_response()
+    }
+}
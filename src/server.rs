@@ -1,30 +1,198 @@
 use crate::{
-    handler::HandlerFunction, parser::first_line_parser::{FirstLineRequestParser, FirstLineResponseParser}, proxy::{ProxyParser, RequestPartProxySender, ResponsePartProxySender}, routing::{ HttpVerb, RoutingMap}, task_manager::{TaskManager, handle}
+    extractor::{FromRef, StateError}, handler::HandlerFunction, listener::Listener, parser::first_line_parser::{FirstLineRequestParser, FirstLineResponseParser}, problem::Problem, proxy::{ProxyParser, RequestPartProxySender, ResponsePartProxySender}, router::Router, response::{IntoResponse, StatusCode}, routing::{ HttpVerb, RoutingMap}, task_manager::{ConnectionSettings, TaskManager, handle}, stream::HttpStream
 };
 use std::{
-    io::Result as IoResult,
-    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
-    sync::Arc,
+    any::type_name,
+    io::{self, ErrorKind, Result as IoResult},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
 };
 
+use signal_hook::consts::{SIGINT, SIGTERM};
+#[cfg(unix)]
+use std::path::PathBuf;
+
+#[cfg(feature = "tls")]
+use rustls::ServerConfig;
+
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+
+#[derive(Clone, Default)]
+pub struct ShutdownHandle(Arc<AtomicBool>);
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    pub fn is_shutting_down(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub struct ServerBuilder {
+    addresses: Vec<SocketAddr>,
+    #[cfg(feature = "tls")]
+    tls_addresses: Vec<(SocketAddr, Arc<ServerConfig>)>,
+    #[cfg(unix)]
+    unix_paths: Vec<PathBuf>,
+    #[cfg(unix)]
+    unix_socket_permissions: Option<u32>,
+    no_of_threads: usize,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            addresses: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls_addresses: Vec::new(),
+            #[cfg(unix)]
+            unix_paths: Vec::new(),
+            #[cfg(unix)]
+            unix_socket_permissions: None,
+            no_of_threads: thread::available_parallelism().map_or(4, |threads| threads.get()),
+        }
+    }
+}
+
+impl ServerBuilder {
+    // every address the input resolves to gets its own listener, so `localhost:8000`
+    // ends up listening on both the IPv4 and the IPv6 loopback
+    pub fn bind<A: ToSocketAddrs>(mut self, address: A) -> IoResult<Self> {
+        self.addresses.extend(address.to_socket_addrs()?);
+        Ok(self)
+    }
+    #[cfg(feature = "tls")]
+    pub fn bind_tls<A: ToSocketAddrs>(mut self, address: A, tls_config: TlsConfig) -> IoResult<Self> {
+        let server_config = tls_config.into_server_config()?;
+        for address in address.to_socket_addrs()? {
+            self.tls_addresses.push((address, Arc::clone(&server_config)));
+        }
+        Ok(self)
+    }
+    #[cfg(unix)]
+    pub fn bind_unix<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.unix_paths.push(path.into());
+        self
+    }
+    #[cfg(unix)]
+    pub fn unix_socket_permissions(mut self, mode: u32) -> Self {
+        self.unix_socket_permissions = Some(mode);
+        self
+    }
+    pub fn threads(mut self, no_of_threads: usize) -> Self {
+        self.no_of_threads = no_of_threads;
+        self
+    }
+    pub fn build(self) -> IoResult<Server> {
+        let mut listeners = self
+            .addresses
+            .iter()
+            .map(Listener::bind_tcp)
+            .collect::<IoResult<Vec<Listener>>>()?;
+        #[cfg(feature = "tls")]
+        for (address, server_config) in &self.tls_addresses {
+            listeners.push(Listener::bind_tls(address, Arc::clone(server_config))?);
+        }
+        #[cfg(unix)]
+        for path in &self.unix_paths {
+            listeners.push(Listener::bind_unix(path, self.unix_socket_permissions)?);
+        }
+        if listeners.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "no address to listen on was given",
+            ));
+        }
+        Ok(Server {
+            listeners,
+            no_of_threads: self.no_of_threads,
+            router: RoutingMap::new(),
+            connection_settings: ConnectionSettings::default(),
+            shutdown: ShutdownHandle::default(),
+            shutdown_timeout: Duration::from_secs(30),
+        })
+    }
+}
+
 pub struct Server {
-    listener: TcpListener,
+    listeners: Vec<Listener>,
     no_of_threads: usize,
     router: RoutingMap,
+    connection_settings: ConnectionSettings,
+    shutdown: ShutdownHandle,
+    shutdown_timeout: Duration,
 }
 
 impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
     pub fn serve(port: u16, no_of_threads: usize) -> IoResult<Self> {
-        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))?;
-        Ok(Server {
-            listener,
-            no_of_threads,
-            router: RoutingMap::new(),
-        })
+        Server::builder()
+            .bind(SocketAddr::from(([127, 0, 0, 1], port)))?
+            .threads(no_of_threads)
+            .build()
+    }
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.listeners
+            .iter()
+            .filter_map(Listener::local_addr)
+            .collect()
+    }
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+    pub fn shutdown_on_signals(&self) -> IoResult<()> {
+        for signal in [SIGTERM, SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(&self.shutdown.0))?;
+        }
+        Ok(())
+    }
+    pub fn set_shutdown_timeout(&mut self, shutdown_timeout: Duration) {
+        self.shutdown_timeout = shutdown_timeout;
+    }
+    pub fn set_keep_alive_timeout(&mut self, keep_alive_timeout: Duration) {
+        self.connection_settings.set_keep_alive_timeout(keep_alive_timeout);
+    }
+    pub fn set_max_requests_per_connection(&mut self, max_requests_per_connection: usize) {
+        self.connection_settings
+            .set_max_requests_per_connection(max_requests_per_connection);
+    }
+    pub fn set_header_read_timeout(&mut self, header_read_timeout: Duration) {
+        self.connection_settings.read_timeouts_mut().header_read = header_read_timeout;
+    }
+    pub fn set_body_read_timeout(&mut self, body_read_timeout: Duration) {
+        self.connection_settings.read_timeouts_mut().body_read = body_read_timeout;
+    }
+    pub fn set_header_deadline(&mut self, header_deadline: Duration) {
+        self.connection_settings.read_timeouts_mut().header_deadline = header_deadline;
+    }
+    pub fn set_write_timeout(&mut self, write_timeout: Duration) {
+        self.connection_settings.set_write_timeout(write_timeout);
+    }
+    pub fn set_max_request_line_length(&mut self, max_request_line_length: usize) {
+        self.connection_settings.parser_limits_mut().max_request_line_length =
+            max_request_line_length;
+    }
+    pub fn set_max_header_bytes(&mut self, max_header_bytes: usize) {
+        self.connection_settings.parser_limits_mut().max_header_bytes = max_header_bytes;
+    }
+    pub fn set_max_header_count(&mut self, max_header_count: usize) {
+        self.connection_settings.parser_limits_mut().max_header_count = max_header_count;
+    }
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.connection_settings.parser_limits_mut().max_body_size = max_body_size;
     }
     pub fn post<Args, F>(
         &mut self,
-        route: &'static str,
+        route: impl Into<String>,
         handler: F,
     ) -> Result<(), matchit::InsertError>
     where
@@ -36,7 +204,7 @@ impl Server {
     }
     pub fn get<Args, F>(
         &mut self,
-        route: &'static str,
+        route: impl Into<String>,
         handler: F,
     ) -> Result<(), matchit::InsertError>
     where
@@ -48,7 +216,7 @@ impl Server {
     }
     pub fn delete<Args, F>(
         &mut self,
-        route: &'static str,
+        route: impl Into<String>,
         handler: F,
     ) -> Result<(), matchit::InsertError>
     where
@@ -58,38 +226,180 @@ impl Server {
         self.router.add_handler(HttpVerb::DELETE, handler, route)?;
         Ok(())
     }
-    pub fn listen(self) {
+    pub fn put<Args, F>(
+        &mut self,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_handler(HttpVerb::PUT, handler, route)?;
+        Ok(())
+    }
+    pub fn patch<Args, F>(
+        &mut self,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_handler(HttpVerb::PATCH, handler, route)?;
+        Ok(())
+    }
+    pub fn head<Args, F>(
+        &mut self,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_handler(HttpVerb::HEAD, handler, route)?;
+        Ok(())
+    }
+    pub fn options<Args, F>(
+        &mut self,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_handler(HttpVerb::OPTIONS, handler, route)?;
+        Ok(())
+    }
+    pub fn route<Args, F>(
+        &mut self,
+        method: HttpVerb,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_handler(method, handler, route)?;
+        Ok(())
+    }
+    pub fn any<Args, F>(
+        &mut self,
+        route: impl Into<String>,
+        handler: F,
+    ) -> Result<(), matchit::InsertError>
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.add_any_handler(handler, route)?;
+        Ok(())
+    }
+    // every state type is stored once and handed out to State<T> extractors as an Arc,
+    // like the other Server setters this takes &mut self, chaining ends at ServerBuilder::build
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) {
+        self.router.state_mut().insert(state);
+    }
+    // derives T from the already registered state S so handlers can extract State<T> directly
+    pub fn with_sub_state<S, T>(&mut self) -> Result<(), StateError>
+    where
+        S: Send + Sync + 'static,
+        T: FromRef<S> + Send + Sync + 'static,
+    {
+        let state = self
+            .router
+            .state::<S>()
+            .ok_or(StateError::Missing(type_name::<S>()))?;
+        self.router.state_mut().insert(T::from_ref(&state));
+        Ok(())
+    }
+    // answers requests no route matches instead of the built in 404
+    pub fn fallback<Args, F>(&mut self, handler: F)
+    where
+        F: HandlerFunction<Args>,
+        Args: Send + Sync + 'static,
+    {
+        self.router.set_fallback(handler);
+    }
+    pub fn error_page<F, R>(&mut self, status_code: StatusCode, renderer: F)
+    where
+        F: Fn(&str) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.router.set_error_page(status_code, renderer);
+    }
+    // renders every extractor rejection, e.g. to match an existing error format
+    pub fn rejection_renderer<F, R>(&mut self, renderer: F)
+    where
+        F: Fn(&Problem) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.router.set_rejection_renderer(renderer);
+    }
+    pub fn nest(&mut self, prefix: &str, router: Router) -> Result<(), matchit::InsertError> {
+        self.router.nest(prefix, router)
+    }
+    pub fn merge(&mut self, router: Router) -> Result<(), matchit::InsertError> {
+        self.router.merge(router)
+    }
+    pub fn listen(self) -> IoResult<()> {
         let task_manager = TaskManager::new(self.no_of_threads);
         let routing_map = Arc::new(self.router);
-        for stream in self.listener.incoming() {
-            println!("new");
-            let stream = match stream {
-                Ok(my_stream) => my_stream,
-                Err(_) => continue,
-            };
+        let connection_settings = Arc::new(self.connection_settings);
+        let shutdown = self.shutdown.clone();
+        accept_connections(&self.listeners, &self.shutdown, |stream| {
             let global_router = Arc::clone(&routing_map);
+            let settings = Arc::clone(&connection_settings);
+            let shutdown = shutdown.clone();
             task_manager.execute(|| {
-                if let Err(err) = handle(stream, global_router) {
+                if let Err(err) = handle(stream, global_router, settings, shutdown) {
                     println!("error occurred handling,{err}");
                 }
             });
-        }
+        });
+        task_manager.shutdown(self.shutdown_timeout)
     }
     pub fn proxy_listen(&self) {
-        for stream in self.listener.incoming() {
-            println!("new");
-            let stream = match stream {
-                Ok(my_stream) => my_stream,
-                Err(_) => continue,
-            };
+        accept_connections(&self.listeners, &self.shutdown, |stream| {
             if let Err(err) = proxy_to_remote(stream) {
                 println!("error occurred handling,{err}");
             }
+        });
+    }
+}
+
+fn accept_connections<F: FnMut(Box<dyn HttpStream>)>(
+    listeners: &[Listener],
+    shutdown: &ShutdownHandle,
+    mut on_connection: F,
+) {
+    while !shutdown.is_shutting_down() {
+        let mut accepted_any = false;
+        for listener in listeners {
+            let stream = match listener.accept() {
+                Ok(my_stream) => my_stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+                // e.g. EMFILE, the sleep below keeps this from spinning until a descriptor frees up
+                Err(err) => {
+                    println!("error accepting a connection,{err}");
+                    continue;
+                }
+            };
+            println!("new");
+            accepted_any = true;
+            on_connection(stream);
+        }
+        if !accepted_any {
+            thread::sleep(Duration::from_millis(50));
         }
     }
 }
 
-fn proxy_to_remote(mut client_stream: TcpStream) -> IoResult<()> {
+fn proxy_to_remote(mut client_stream: Box<dyn HttpStream>) -> IoResult<()> {
     let host = "httpbin.org:80";
     let ip_lookup = host.to_socket_addrs()?.next().unwrap();
     let mut connection = TcpStream::connect(ip_lookup).unwrap();
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind, Result as IoResult},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    cookie::{Cookie, CookieJar, SameSite},
+    extractor::FromRequest,
+    handler::Service,
+    parser::http_message_parser::Request,
+    response::{IntoResponse, Response},
+    router::Middleware,
+    routing::RoutingMap,
+};
+
+pub type SessionData = HashMap<String, Value>;
+
+pub trait SessionStore: Send + Sync + 'static {
+    // None when the session does not exist or has expired
+    fn load(&self, id: &str) -> IoResult<Option<SessionData>>;
+    fn save(&self, id: &str, data: &SessionData, expires_at: SystemTime) -> IoResult<()>;
+    fn delete(&self, id: &str) -> IoResult<()>;
+}
+
+// sessions that are never loaded again would otherwise stay in memory forever
+const SWEEP_INTERVAL: usize = 64;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (SessionData, SystemTime)>>,
+    saves: AtomicUsize,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // drops every expired session, load already ignores them
+    pub fn remove_expired(&self) {
+        let now = SystemTime::now();
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, (_, expires_at)| *expires_at > now);
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> IoResult<Option<SessionData>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(id) {
+            Some((_, expires_at)) if *expires_at <= SystemTime::now() => {
+                sessions.remove(id);
+                Ok(None)
+            }
+            Some((data, _)) => Ok(Some(data.clone())),
+            None => Ok(None),
+        }
+    }
+    fn save(&self, id: &str, data: &SessionData, expires_at: SystemTime) -> IoResult<()> {
+        if self.saves.fetch_add(1, Ordering::Relaxed).is_multiple_of(SWEEP_INTERVAL) {
+            self.remove_expired();
+        }
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), (data.clone(), expires_at));
+        Ok(())
+    }
+    fn delete(&self, id: &str) -> IoResult<()> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    expires_at: u64,
+    data: SessionData,
+}
+
+// one json file per session inside the directory
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(directory: impl Into<PathBuf>) -> IoResult<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+    fn session_path(&self, id: &str) -> IoResult<PathBuf> {
+        // ids come from the client, anything that is not one of ours must not reach the filesystem
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "invalid session id"));
+        }
+        Ok(self.directory.join(format!("{}.json", id)))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> IoResult<Option<SessionData>> {
+        let Ok(path) = self.session_path(id) else {
+            return Ok(None);
+        };
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let stored: StoredSession = serde_json::from_slice(&contents)?;
+        if UNIX_EPOCH + Duration::from_secs(stored.expires_at) <= SystemTime::now() {
+            self.delete(id)?;
+            return Ok(None);
+        }
+        Ok(Some(stored.data))
+    }
+    fn save(&self, id: &str, data: &SessionData, expires_at: SystemTime) -> IoResult<()> {
+        let stored = StoredSession {
+            expires_at: expires_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            data: data.clone(),
+        };
+        let path = self.session_path(id)?;
+        // written next to the target and renamed so a crash never leaves half a session behind,
+        // the random suffix keeps concurrent saves of one session from sharing a file
+        let mut suffix = [0; 8];
+        getrandom::fill(&mut suffix).map_err(io::Error::other)?;
+        let suffix: String = suffix.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let temporary_path = path.with_extension(format!("json.{}.tmp", suffix));
+        fs::write(&temporary_path, serde_json::to_vec(&stored)?)?;
+        fs::rename(temporary_path, path)
+    }
+    fn delete(&self, id: &str) -> IoResult<()> {
+        match fs::remove_file(self.session_path(id)?) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SessionState {
+    id: Option<String>,
+    data: SessionData,
+    modified: bool,
+    destroyed: bool,
+}
+
+// shared between the layer and the handler, clones point at the same session
+#[derive(Clone, Default)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl Session {
+    fn load(id: Option<String>, data: SessionData) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SessionState {
+                id,
+                data,
+                ..SessionState::default()
+            })),
+        }
+    }
+    pub fn id(&self) -> Option<String> {
+        self.state.lock().unwrap().id.clone()
+    }
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let state = self.state.lock().unwrap();
+        serde_json::from_value(state.data.get(key)?.clone()).ok()
+    }
+    pub fn insert<T: Serialize>(&self, key: &str, value: T) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_value(value)?;
+        let mut state = self.state.lock().unwrap();
+        state.data.insert(key.to_string(), value);
+        state.modified = true;
+        Ok(())
+    }
+    pub fn remove(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.data.remove(key).is_some() {
+            state.modified = true;
+        }
+    }
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.modified = true;
+    }
+    // deletes the session from the store and expires the cookie once the handler returns
+    pub fn destroy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+    // a fresh id for the same data, call it after a login to avoid session fixation
+    pub fn regenerate(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.id = None;
+        state.modified = true;
+    }
+}
+
+impl FromRequest for Session {
+    type Error = SessionError;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        match request.extensions().get::<Session>() {
+            Some(session) => Ok(Session::clone(&session)),
+            None => Err(SessionError::MissingLayer),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("the session extractor needs the route to be wrapped in a SessionLayer")]
+    MissingLayer,
+    #[error("session store error: {0}")]
+    Store(#[from] io::Error),
+}
+
+pub struct SessionLayer {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl SessionLayer {
+    pub fn new<S: SessionStore>(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            cookie_name: "session_id".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+        }
+    }
+    pub fn cookie_name(mut self, cookie_name: impl Into<String>) -> Self {
+        self.cookie_name = cookie_name.into();
+        self
+    }
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+    fn cookie(&self, id: &str) -> Cookie {
+        Cookie::new(self.cookie_name.as_str(), id)
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+    }
+    fn load_session(&self, request: &Request) -> IoResult<Session> {
+        let jar = CookieJar::from_request_headers(request);
+        let Some(id) = jar.get(&self.cookie_name).map(|cookie| cookie.value().to_string()) else {
+            return Ok(Session::default());
+        };
+        match self.store.load(&id)? {
+            Some(data) => Ok(Session::load(Some(id), data)),
+            None => Ok(Session::default()),
+        }
+    }
+    fn persist_session(&self, session: &Session, response: &mut Response) -> IoResult<()> {
+        let mut state = session.state.lock().unwrap();
+        if state.destroyed {
+            if let Some(id) = state.id.take() {
+                self.store.delete(&id)?;
+                let mut removal = CookieJar::new();
+                removal.remove(self.cookie(""));
+                for cookie in removal.delta() {
+                    response.append_header("Set-Cookie", &cookie.encode());
+                }
+            }
+            return Ok(());
+        }
+        if !state.modified {
+            return Ok(());
+        }
+        let id = match &state.id {
+            Some(id) => id.clone(),
+            None => generate_session_id()?,
+        };
+        self.store
+            .save(&id, &state.data, SystemTime::now() + self.ttl)?;
+        state.id = Some(id.clone());
+        let cookie = self.cookie(&id).max_age(self.ttl);
+        response.append_header("Set-Cookie", &cookie.encode());
+        Ok(())
+    }
+}
+
+impl Middleware for SessionLayer {
+    fn handle(&self, mut request: Request, next: &dyn Service) -> Response {
+        let routing = request.routing();
+        let session = match self.load_session(&request) {
+            Ok(session) => session,
+            Err(err) => return session_store_failure(&routing, err),
+        };
+        // a regenerated session leaves the old id behind, remember it so it can be removed
+        let loaded_id = session.id();
+        request.extensions_mut().insert(session.clone());
+        let mut response = next.call(request);
+        if let Some(loaded_id) = loaded_id
+            && session.id().is_none()
+            && let Err(err) = self.store.delete(&loaded_id)
+        {
+            return session_store_failure(&routing, err);
+        }
+        match self.persist_session(&session, &mut response) {
+            Ok(()) => response,
+            Err(err) => session_store_failure(&routing, err),
+        }
+    }
+}
+
+// rendered like any other rejection so a custom rejection renderer covers it too
+fn session_store_failure(routing: &RoutingMap, err: io::Error) -> Response {
+    routing.render_rejection(SessionError::Store(err).into_response())
+}
+
+fn generate_session_id() -> IoResult<String> {
+    let mut bytes = [0; 32];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
@@ -0,0 +1,72 @@
+use std::{
+    io::{Read, Result as IoResult, Write},
+    net::{Shutdown, TcpStream},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::mock_stream::MockStream;
+
+pub trait HttpStream: Read + Write + Send + 'static {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()>;
+    fn shutdown(&self, how: Shutdown) -> IoResult<()>;
+    // called once the server is done with the connection, before it is dropped
+    fn close(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+impl HttpStream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+    fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        TcpStream::shutdown(self, how)
+    }
+}
+
+#[cfg(unix)]
+impl HttpStream for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+    fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        UnixStream::shutdown(self, how)
+    }
+}
+
+impl HttpStream for Box<dyn HttpStream> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        (**self).set_read_timeout(timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        (**self).set_write_timeout(timeout)
+    }
+    fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        (**self).shutdown(how)
+    }
+    fn close(&mut self) -> IoResult<()> {
+        (**self).close()
+    }
+}
+
+impl HttpStream for MockStream {
+    fn set_read_timeout(&self, _: Option<Duration>) -> IoResult<()> {
+        Ok(())
+    }
+    fn set_write_timeout(&self, _: Option<Duration>) -> IoResult<()> {
+        Ok(())
+    }
+    fn shutdown(&self, _: Shutdown) -> IoResult<()> {
+        Ok(())
+    }
+}
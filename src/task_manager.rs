@@ -1,14 +1,16 @@
 use std::{
-    io::{Result as IoResult, Write},
-    net::TcpStream,
+    io::{self, ErrorKind, Read, Result as IoResult},
+    panic::{self, AssertUnwindSafe},
+    net::Shutdown,
     sync::{
         mpsc::{self, Receiver, Sender}, Arc, Mutex
     },
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 
-use crate::{parser::{ first_line_parser::FirstLineRequestParser, http_message_parser::{Parser}}, response::{get_common_headers, get_preflight_headers, write_headers, write_response_headers, write_response_status_line, write_status_line, ContentType, Response, StatusCode}, response_writer::ResponseWriter, routing::{HttpVerb, RoutingMap}};
+use crate::{body_reader::BodyReader, headers::HeaderMap, parser::{ first_line_parser::{FirstLineRequestParser, RequestLine}, http_message_parser::{MessageParseError, Parser, ParserLimits, ReadTimeouts, Request}}, response::{connection_header_value, get_common_headers_with_content_type_header, get_preflight_headers, write_response_headers, write_response_status_line, ContentType, Response, StatusCode}, response_writer::ResponseWriter, handler::Service, routing::{HttpVerb, RoutingMap}, server::ShutdownHandle, stream::HttpStream};
 
 
 
@@ -41,6 +43,34 @@ impl TaskManager {
         self.tramsmitter.as_ref().unwrap().send(job).unwrap();
     }
 }
+impl TaskManager {
+    // stops handing out new jobs, lets the workers finish what is already queued and
+    // gives up on the ones still running once the timeout has passed
+    pub fn shutdown(mut self, timeout: Duration) -> IoResult<()> {
+        drop(self.tramsmitter.take());
+        let deadline = Instant::now() + timeout;
+        while self.workers.iter().any(|worker| !worker.task_handle.is_finished())
+            && Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut still_busy = 0;
+        for worker in self.workers.drain(..) {
+            if worker.task_handle.is_finished() {
+                worker.task_handle.join().unwrap();
+            } else {
+                still_busy += 1;
+            }
+        }
+        if still_busy > 0 {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("{} worker threads still busy after the shutdown deadline", still_busy),
+            ));
+        }
+        Ok(())
+    }
+}
 impl Drop for TaskManager {
     fn drop(&mut self) {
         drop(self.tramsmitter.take());
@@ -78,56 +108,191 @@ impl Worker {
     }
 }
 
-pub fn handle(mut connection: TcpStream, custom_handler: Arc<RoutingMap>) -> IoResult<()>
+#[derive(Clone, Copy)]
+pub struct ConnectionSettings {
+    keep_alive_timeout: Duration,
+    max_requests_per_connection: usize,
+    read_timeouts: ReadTimeouts,
+    write_timeout: Duration,
+    parser_limits: ParserLimits,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        Self {
+            keep_alive_timeout: Duration::from_secs(5),
+            max_requests_per_connection: 100,
+            read_timeouts: ReadTimeouts::default(),
+            write_timeout: Duration::from_secs(30),
+            parser_limits: ParserLimits::default(),
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn keep_alive_timeout(&self) -> Duration {
+        self.keep_alive_timeout
+    }
+    pub fn set_keep_alive_timeout(&mut self, keep_alive_timeout: Duration) {
+        self.keep_alive_timeout = keep_alive_timeout;
+    }
+    pub fn max_requests_per_connection(&self) -> usize {
+        self.max_requests_per_connection
+    }
+    pub fn set_max_requests_per_connection(&mut self, max_requests_per_connection: usize) {
+        self.max_requests_per_connection = max_requests_per_connection.max(1);
+    }
+    pub fn read_timeouts(&self) -> ReadTimeouts {
+        self.read_timeouts
+    }
+    pub fn read_timeouts_mut(&mut self) -> &mut ReadTimeouts {
+        &mut self.read_timeouts
+    }
+    pub fn write_timeout(&self) -> Duration {
+        self.write_timeout
+    }
+    pub fn set_write_timeout(&mut self, write_timeout: Duration) {
+        self.write_timeout = write_timeout;
+    }
+    pub fn parser_limits(&self) -> ParserLimits {
+        self.parser_limits
+    }
+    pub fn parser_limits_mut(&mut self) -> &mut ParserLimits {
+        &mut self.parser_limits
+    }
+}
+
+pub fn handle<S: HttpStream>(connection: S, custom_handler: Arc<RoutingMap>, settings: Arc<ConnectionSettings>, shutdown: ShutdownHandle) -> IoResult<()>
 {
-    let request_parser = Parser::new(FirstLineRequestParser::default());
-    match request_parser.parse(&mut connection) {
-        Ok(payload_request) => {
-            let routing=Arc::clone(&custom_handler);
-            let request=payload_request.from(routing);
-            if request.request_method() == HttpVerb::OPTIONS {
-                write_status_line(&mut connection, StatusCode::Ok)?;
-                let headers = get_preflight_headers();
-                write_headers(&mut connection, headers)?;
-                return Ok(());
+    // shared so a handler's BodyReader can pull the body off the connection
+    let connection = Arc::new(Mutex::new(connection));
+    let served = serve_connection(&connection, custom_handler, settings, shutdown);
+    // the peer may already be gone, nothing left to report in that case
+    let _ = connection.lock().unwrap().close();
+    served
+}
 
+fn serve_connection<S: HttpStream>(connection: &Arc<Mutex<S>>, custom_handler: Arc<RoutingMap>, settings: Arc<ConnectionSettings>, shutdown: ShutdownHandle) -> IoResult<()>
+{
+    connection.lock().unwrap().set_write_timeout(Some(settings.write_timeout()))?;
+    let mut buffered_data = Vec::new();
+    let mut requests_served = 0;
+    loop {
+        let mut stream = connection.lock().unwrap();
+        // the parser switches to the header and body timeouts once the request starts
+        stream.set_read_timeout(Some(settings.keep_alive_timeout()))?;
+        let routing = Arc::clone(&custom_handler);
+        let request_parser = Parser::with_buffered_data(FirstLineRequestParser::default(), buffered_data)
+            .with_timeouts(settings.read_timeouts())
+            .with_limits(settings.parser_limits())
+            .with_streamed_body(move |request_line: &RequestLine| {
+                let path = request_line.request_target().split('?').next().unwrap_or_default();
+                routing.streams_body(&HttpVerb::from(request_line.method()), path)
+            });
+        let parsed = request_parser.parse(&mut *stream);
+        match parsed {
+            Ok(mut payload_request) => {
+                requests_served += 1;
+                let body_streamed = payload_request.body_streamed();
+                let mut leftover = payload_request.take_leftover();
+                let routing=Arc::clone(&custom_handler);
+                let mut request=payload_request.from(routing);
+                let mut body_reader = None;
+                if body_streamed {
+                    stream.set_read_timeout(Some(settings.read_timeouts().body_read))?;
+                    let source: Arc<Mutex<dyn Read + Send>> = connection.clone();
+                    let reader = BodyReader::new(source, leftover.take().unwrap_or_default(), request.headers(), settings.parser_limits().max_body_size)?;
+                    request.extensions_mut().insert(reader.clone());
+                    body_reader = Some(reader);
+                }
+                // the handler may read from the connection, it must not be locked meanwhile
+                drop(stream);
+                let wants_keep_alive = (leftover.is_some() || body_streamed)
+                    && request.keep_alive()
+                    && requests_served < settings.max_requests_per_connection();
+                let (mut sending_response, send_body) = respond(request, &custom_handler);
+                // checked after the handler ran, a shutdown may have started meanwhile
+                let keep_alive = wants_keep_alive && !shutdown.is_shutting_down();
+                sending_response.set_header("Connection", connection_header_value(keep_alive));
+                send_response_to_network(&mut *connection.lock().unwrap(), sending_response, send_body)?;
+                if let Some(body_reader) = body_reader {
+                    // the rest of an unread body has to go before the next request can be parsed
+                    leftover = body_reader.finish().ok();
+                }
+                match leftover {
+                    Some(leftover) if keep_alive => buffered_data = leftover,
+                    _ => return Ok(()),
+                }
             }
-            let handler_function=match custom_handler.get_handler(&request.request_method(), request.request_path()){
-                Some(val) => val,
-                None => {
-                    write_status_line(&mut connection, StatusCode::NotFound)?;
-                    let headers = get_common_headers();
-                    write_headers(&mut connection, headers)?;
-                    return Ok(());
-
-                },
-            };
-            let sending_response=handler_function.call(request);
-            send_response_to_network(connection, sending_response)?;
-            Ok(())
-        }
-        Err(err) => {
-            if err == "false alarm" {
-                connection.shutdown(std::net::Shutdown::Both)?;
+            Err(MessageParseError::ConnectionClosed) => {
+                stream.shutdown(Shutdown::Both)?;
+                return Ok(());
+            }
+            Err(err) => {
+                if let Some(mut sending_response) = custom_handler.render_error(err.status_code(), &err.to_string()) {
+                    sending_response.set_header("Connection", connection_header_value(false));
+                    return send_response_to_network(&mut *stream, sending_response, true);
+                }
+                let response_writer = ResponseWriter::new(&mut *stream);
+                response_writer
+                    .write_status_line(err.status_code())?
+                    .write_default_headers(ContentType::TextPlain)?
+                    .write_body_plain_text(&err.to_string())?;
                 return Ok(());
             }
-            let response_writer = ResponseWriter::new(&mut connection);
-            response_writer
-                .write_status_line(StatusCode::BadRequest)?
-                .write_default_headers(ContentType::TextPlain)?
-                .write_body_plain_text(&err)?;
-            Ok(())
         }
     }
 }
 
-fn send_response_to_network(mut connection:TcpStream,sending_response:Response)->IoResult<()>{
-    write_response_status_line(&mut connection,sending_response.status_code() )?;
-    write_response_headers(&mut connection, sending_response.headers())?;
-    if !sending_response.body().is_empty(){
-        connection.write_all(sending_response.body())?;
+// the response and whether its body goes on the wire
+fn respond(request: Request, custom_handler: &RoutingMap) -> (Response, bool) {
+    let is_head = request.request_method() == HttpVerb::HEAD;
+    let sending_response = match custom_handler.get_handler(&request.request_method(), request.request_path()) {
+        Some(handler_function) => call_handler(handler_function, request, custom_handler),
+        None => {
+            let allowed_methods = custom_handler.allowed_methods(request.request_path());
+            let allow = allowed_methods.iter().map(HttpVerb::as_str).collect::<Vec<_>>().join(", ");
+            if allowed_methods.is_empty() {
+                match custom_handler.fallback() {
+                    Some(fallback) => call_handler(fallback, request, custom_handler),
+                    None => error_response(custom_handler, StatusCode::NotFound, "not found"),
+                }
+            } else if request.request_method() == HttpVerb::OPTIONS {
+                let mut headers = HeaderMap::from(get_preflight_headers());
+                headers.insert("Access-Control-Allow-Methods", allow.as_str());
+                headers.insert("Allow", allow);
+                Response::new(StatusCode::Ok.status_message(), StatusCode::Ok, headers, Vec::new())
+            } else {
+                let mut response = error_response(custom_handler, StatusCode::MethodNotAllowed, "method not allowed");
+                response.set_header("Allow", &allow);
+                response
+            }
+        }
+    };
+    // a HEAD reply keeps the headers, Content-Length included, but never carries the body
+    (sending_response, !is_head)
+}
+
+// a panicking handler only costs its own request a 500, the connection and worker live on
+fn call_handler(handler_function: &dyn Service, request: Request, custom_handler: &RoutingMap) -> Response {
+    match panic::catch_unwind(AssertUnwindSafe(|| handler_function.call(request))) {
+        Ok(response) => response,
+        Err(_) => error_response(custom_handler, StatusCode::InternalServerError, "internal server error"),
     }
-    Ok(())
 }
 
+fn error_response(custom_handler: &RoutingMap, status_code: StatusCode, message: &str) -> Response {
+    custom_handler.render_error(status_code, message).unwrap_or_else(|| {
+        let headers = get_common_headers_with_content_type_header(message.as_bytes(), ContentType::TextPlain);
+        Response::new(status_code.status_message(), status_code, headers, message.as_bytes().to_vec())
+    })
+}
 
+fn send_response_to_network<S: HttpStream>(connection:&mut S,sending_response:Response,send_body:bool)->IoResult<()>{
+    write_response_status_line(connection,sending_response.status_code() )?;
+    write_response_headers(connection, sending_response.headers())?;
+    if send_body && !sending_response.body().is_empty(){
+        connection.write_all(sending_response.body())?;
+    }
+    Ok(())
+}
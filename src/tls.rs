@@ -0,0 +1,168 @@
+use std::{
+    fmt,
+    io::{self, Result as IoResult, Write},
+    net::{Shutdown, TcpStream},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use rustls::{
+    ServerConfig, ServerConnection, StreamOwned,
+    crypto::{CryptoProvider, ring},
+    server::{ClientHello, ResolvesServerCert, ResolvesServerCertUsingSni},
+    sign::CertifiedKey,
+};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+
+use crate::stream::HttpStream;
+
+pub type TlsStream = StreamOwned<ServerConnection, TcpStream>;
+
+// certificates picked by the server name the client asks for, falling back to the
+// default one when there is no match or the client sent no SNI at all
+pub struct TlsConfig {
+    provider: Arc<CryptoProvider>,
+    default_certificate: Option<Arc<CertifiedKey>>,
+    sni_certificates: ResolvesServerCertUsingSni,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            provider: Arc::new(ring::default_provider()),
+            default_certificate: None,
+            sni_certificates: ResolvesServerCertUsingSni::new(),
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn from_pem_files<C: AsRef<Path>, K: AsRef<Path>>(
+        certificate_path: C,
+        key_path: K,
+    ) -> IoResult<Self> {
+        let mut tls_config = TlsConfig::default();
+        let certificate = tls_config.load_pem_files(certificate_path, key_path)?;
+        tls_config.default_certificate = Some(Arc::new(certificate));
+        Ok(tls_config)
+    }
+    pub fn from_pem(certificate_pem: &[u8], key_pem: &[u8]) -> IoResult<Self> {
+        let mut tls_config = TlsConfig::default();
+        let certificate = tls_config.load_pem(certificate_pem, key_pem)?;
+        tls_config.default_certificate = Some(Arc::new(certificate));
+        Ok(tls_config)
+    }
+    pub fn add_sni_pem_files<C: AsRef<Path>, K: AsRef<Path>>(
+        mut self,
+        server_name: &str,
+        certificate_path: C,
+        key_path: K,
+    ) -> IoResult<Self> {
+        let certificate = self.load_pem_files(certificate_path, key_path)?;
+        self.sni_certificates
+            .add(server_name, certificate)
+            .map_err(io::Error::other)?;
+        Ok(self)
+    }
+    pub fn add_sni_pem(
+        mut self,
+        server_name: &str,
+        certificate_pem: &[u8],
+        key_pem: &[u8],
+    ) -> IoResult<Self> {
+        let certificate = self.load_pem(certificate_pem, key_pem)?;
+        self.sni_certificates
+            .add(server_name, certificate)
+            .map_err(io::Error::other)?;
+        Ok(self)
+    }
+    pub fn into_server_config(self) -> IoResult<Arc<ServerConfig>> {
+        let provider = Arc::clone(&self.provider);
+        let server_config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(CertificateResolver {
+                default_certificate: self.default_certificate,
+                sni_certificates: self.sni_certificates,
+            }));
+        Ok(Arc::new(server_config))
+    }
+
+    fn load_pem_files<C: AsRef<Path>, K: AsRef<Path>>(
+        &self,
+        certificate_path: C,
+        key_path: K,
+    ) -> IoResult<CertifiedKey> {
+        let certificate_chain = CertificateDer::pem_file_iter(certificate_path)
+            .map_err(io::Error::other)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io::Error::other)?;
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(io::Error::other)?;
+        self.certified_key(certificate_chain, key)
+    }
+    fn load_pem(&self, certificate_pem: &[u8], key_pem: &[u8]) -> IoResult<CertifiedKey> {
+        let certificate_chain = CertificateDer::pem_slice_iter(certificate_pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io::Error::other)?;
+        let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(io::Error::other)?;
+        self.certified_key(certificate_chain, key)
+    }
+    fn certified_key(
+        &self,
+        certificate_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> IoResult<CertifiedKey> {
+        if certificate_chain.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no certificate found in pem data",
+            ));
+        }
+        let signing_key = self
+            .provider
+            .key_provider
+            .load_private_key(key)
+            .map_err(io::Error::other)?;
+        Ok(CertifiedKey::new(certificate_chain, signing_key))
+    }
+}
+
+struct CertificateResolver {
+    default_certificate: Option<Arc<CertifiedKey>>,
+    sni_certificates: ResolvesServerCertUsingSni,
+}
+
+impl fmt::Debug for CertificateResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateResolver").finish_non_exhaustive()
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        if client_hello.server_name().is_some()
+            && let Some(certificate) = self.sni_certificates.resolve(client_hello)
+        {
+            return Some(certificate);
+        }
+        self.default_certificate.clone()
+    }
+}
+
+impl HttpStream for TlsStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        self.sock.set_write_timeout(timeout)
+    }
+    fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        self.sock.shutdown(how)
+    }
+    fn close(&mut self) -> IoResult<()> {
+        self.conn.send_close_notify();
+        self.flush()
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    ops::Deref,
+};
+
+use serde::Serialize;
+use serde_json::json;
+
+pub use regex::Regex;
+pub use single_threaded_server_derive::Validate;
+
+use crate::{
+    extractor::{Form, FromRequest, FromRequestBody, Json, Path, Query},
+    parser::http_message_parser::Request,
+    problem::Problem,
+    response::{IntoResponse, Response, StatusCode},
+};
+
+// checks run on a value after it was deserialized, usually derived:
+//
+// #[derive(Deserialize, Validate)]
+// struct Signup {
+//     #[validate(length(min = 3, max = 20))]
+//     username: String,
+//     #[validate(email)]
+//     email: String,
+//     #[validate(range(min = 18, message = "adults only"))]
+//     age: u8,
+// }
+//
+// rules are length, range, regex, email and nested, every rule takes an optional message
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Violation {
+    // dotted path of the field, `items[2].name` for nested values
+    pub field: String,
+    pub rule: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationErrors {
+    violations: Vec<Violation>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(&mut self, field: impl Into<String>, rule: impl Into<String>, message: impl Into<String>) {
+        self.violations.push(Violation {
+            field: field.into(),
+            rule: rule.into(),
+            message: message.into(),
+        });
+    }
+    // takes over the violations of a nested value with their fields under `prefix`
+    pub fn merge(&mut self, prefix: &str, nested: ValidationErrors) {
+        for mut violation in nested.violations {
+            violation.field = match violation.field.as_str() {
+                "" => prefix.to_string(),
+                field if field.starts_with('[') => format!("{}{}", prefix, field),
+                field => format!("{}.{}", prefix, field),
+            };
+            self.violations.push(violation);
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let violations: Vec<String> = self
+            .violations
+            .iter()
+            .map(|violation| format!("{}: {}", violation.field, violation.message))
+            .collect();
+        write!(f, "{}", violations.join(", "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        Problem::new(StatusCode::UnprocessableEntity, "request failed validation")
+            .with_members(json!({ "errors": self.violations }))
+            .into_response()
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            Some(value) => value.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        for (index, value) in self.iter().enumerate() {
+            if let Err(nested) = value.validate() {
+                errors.merge(&format!("[{}]", index), nested);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+macro_rules! validate_extractor {
+    ($($extractor:ident),*) => {
+        $(
+            impl<T: Validate> Validate for $extractor<T> {
+                fn validate(&self) -> Result<(), ValidationErrors> {
+                    self.0.validate()
+                }
+            }
+        )*
+    };
+}
+
+validate_extractor!(Json, Form, Query, Path);
+
+// runs the extractor and then validates what it produced, `Valid(Json(signup)): Valid<Json<Signup>>`
+pub struct Valid<E>(pub E);
+
+impl<E> Deref for Valid<E> {
+    type Target = E;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<E> FromRequest for Valid<E>
+where
+    E: FromRequest + Validate,
+{
+    type Error = ValidRejection<E::Error>;
+    fn from_request(request: &Request) -> Result<Self, Self::Error> {
+        let extracted = E::from_request(request).map_err(ValidRejection::Extractor)?;
+        extracted.validate().map_err(ValidRejection::Invalid)?;
+        Ok(Valid(extracted))
+    }
+}
+
+impl<E> FromRequestBody for Valid<E>
+where
+    E: FromRequestBody + Validate,
+{
+    const STREAMS_BODY: bool = E::STREAMS_BODY;
+    type Error = ValidRejection<E::Error>;
+    fn from_request_body(request: &Request) -> Result<Self, Self::Error> {
+        let extracted = E::from_request_body(request).map_err(ValidRejection::Extractor)?;
+        extracted.validate().map_err(ValidRejection::Invalid)?;
+        Ok(Valid(extracted))
+    }
+}
+
+#[derive(Debug)]
+pub enum ValidRejection<R> {
+    // the inner extractor already failed, nothing was validated
+    Extractor(R),
+    Invalid(ValidationErrors),
+}
+
+impl<R: IntoResponse> IntoResponse for ValidRejection<R> {
+    fn into_response(self) -> Response {
+        match self {
+            ValidRejection::Extractor(rejection) => rejection.into_response(),
+            ValidRejection::Invalid(errors) => errors.into_response(),
+        }
+    }
+}
+
+// the checks behind the derived rules, each returns the default message when it fails
+pub mod rules {
+    use super::*;
+
+    pub trait HasLength {
+        // None skips the check, e.g. for a missing optional value
+        fn length(&self) -> Option<usize>;
+    }
+
+    impl HasLength for str {
+        fn length(&self) -> Option<usize> {
+            Some(self.chars().count())
+        }
+    }
+
+    impl HasLength for String {
+        fn length(&self) -> Option<usize> {
+            self.as_str().length()
+        }
+    }
+
+    impl<T> HasLength for Vec<T> {
+        fn length(&self) -> Option<usize> {
+            Some(self.len())
+        }
+    }
+
+    impl<T> HasLength for [T] {
+        fn length(&self) -> Option<usize> {
+            Some(self.len())
+        }
+    }
+
+    impl<K, V, S> HasLength for HashMap<K, V, S> {
+        fn length(&self) -> Option<usize> {
+            Some(self.len())
+        }
+    }
+
+    impl<K, V> HasLength for BTreeMap<K, V> {
+        fn length(&self) -> Option<usize> {
+            Some(self.len())
+        }
+    }
+
+    impl<T: HasLength + ?Sized> HasLength for &T {
+        fn length(&self) -> Option<usize> {
+            (**self).length()
+        }
+    }
+
+    impl<T: HasLength> HasLength for Option<T> {
+        fn length(&self) -> Option<usize> {
+            self.as_ref()?.length()
+        }
+    }
+
+    pub trait RangeValue {
+        type Value: PartialOrd + Display;
+        fn range_value(&self) -> Option<&Self::Value>;
+    }
+
+    macro_rules! range_value {
+        ($($number:ty),*) => {
+            $(
+                impl RangeValue for $number {
+                    type Value = $number;
+                    fn range_value(&self) -> Option<&Self::Value> {
+                        Some(self)
+                    }
+                }
+            )*
+        };
+    }
+
+    range_value!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+    impl<T: RangeValue> RangeValue for Option<T> {
+        type Value = T::Value;
+        fn range_value(&self) -> Option<&Self::Value> {
+            self.as_ref()?.range_value()
+        }
+    }
+
+    pub trait AsText {
+        fn as_text(&self) -> Option<&str>;
+    }
+
+    impl AsText for str {
+        fn as_text(&self) -> Option<&str> {
+            Some(self)
+        }
+    }
+
+    impl AsText for String {
+        fn as_text(&self) -> Option<&str> {
+            Some(self)
+        }
+    }
+
+    impl<T: AsText + ?Sized> AsText for &T {
+        fn as_text(&self) -> Option<&str> {
+            (**self).as_text()
+        }
+    }
+
+    impl<T: AsText> AsText for Option<T> {
+        fn as_text(&self) -> Option<&str> {
+            self.as_ref()?.as_text()
+        }
+    }
+
+    pub fn length<T: HasLength + ?Sized>(value: &T, min: Option<usize>, max: Option<usize>) -> Result<(), String> {
+        let Some(length) = value.length() else {
+            return Ok(());
+        };
+        match (min, max) {
+            (Some(min), Some(max)) if length < min || length > max => {
+                Err(format!("length must be between {} and {}", min, max))
+            }
+            (Some(min), None) if length < min => Err(format!("length must be at least {}", min)),
+            (None, Some(max)) if length > max => Err(format!("length must be at most {}", max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn range<T: RangeValue + ?Sized>(value: &T, min: Option<T::Value>, max: Option<T::Value>) -> Result<(), String> {
+        let Some(value) = value.range_value() else {
+            return Ok(());
+        };
+        match (min, max) {
+            (Some(min), Some(max)) if *value < min || *value > max => {
+                Err(format!("must be between {} and {}", min, max))
+            }
+            (Some(min), None) if *value < min => Err(format!("must be at least {}", min)),
+            (None, Some(max)) if *value > max => Err(format!("must be at most {}", max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn regex<T: AsText + ?Sized>(value: &T, regex: &Regex) -> Result<(), String> {
+        match value.as_text() {
+            Some(text) if !regex.is_match(text) => Err(format!("must match {}", regex.as_str())),
+            _ => Ok(()),
+        }
+    }
+
+    // a deliberately loose check, a confirmation mail is the only real proof
+    pub fn email<T: AsText + ?Sized>(value: &T) -> Result<(), String> {
+        let Some(text) = value.as_text() else {
+            return Ok(());
+        };
+        let valid = match text.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.split('.').count() > 1
+                    && domain.split('.').all(|label| !label.is_empty())
+                    && !text.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+        if valid { Ok(()) } else { Err("must be a valid email address".to_string()) }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    io::{self, ErrorKind, Read},
+    sync::{Arc, Mutex},
+};
+
+use single_threaded_server::{
+    body_reader::BodyReader,
+    headers::HeaderMap,
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+    task_manager::ConnectionSettings,
+};
+
+mod common;
+
+// over a real socket, the handlers read the body off the connection themselves
+fn exchange(routing: RoutingMap, request: &[u8]) -> String {
+    common::exchange_over_tcp(Arc::new(routing), ConnectionSettings::default(), request)
+}
+
+#[test]
+fn streams_chunked_body_and_keeps_the_connection() {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |mut body: BodyReader| {
+                let mut upload = String::new();
+                body.read_to_string(&mut upload).unwrap();
+                Html::new(format!("got {}", upload))
+            },
+            "/upload",
+        )
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("done".to_string()), "/done")
+        .unwrap();
+    let responses = exchange(
+        routing,
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+          5\r\nhel\r\n\r\n6\r\n world\r\n0\r\nChecksum: 1\r\n\r\n\
+          GET /done HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(responses.contains("\r\n\r\ngot hel\r\n world"));
+    assert!(responses.ends_with("\r\n\r\ndone"));
+}
+
+#[test]
+fn drains_unread_body_before_the_next_request() {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |mut body: BodyReader| {
+                let mut first = [0; 4];
+                body.read_exact(&mut first).unwrap();
+                Html::new(String::from_utf8_lossy(&first).into_owned())
+            },
+            "/peek",
+        )
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::POST, |body: String| Html::new(body), "/echo")
+        .unwrap();
+    let responses = exchange(
+        routing,
+        b"POST /peek HTTP/1.1\r\nHost: localhost\r\nContent-Length: 11\r\n\r\nhello world\
+          POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n\xff\xfe\
+          POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\nConnection: close\r\n\r\nagain",
+    );
+    assert!(responses.contains("\r\n\r\nhell"));
+    assert!(responses.contains("HTTP/1.1 400 Bad Request"));
+    assert!(responses.ends_with("\r\n\r\nagain"));
+}
+
+#[test]
+fn rejects_an_invalid_content_length() {
+    let reader = |content_length: &str| {
+        let headers: HeaderMap = [("Content-Length", content_length)].into_iter().collect();
+        BodyReader::new(Arc::new(Mutex::new(io::empty())), b"hello".to_vec(), &headers, 1024)
+    };
+    for content_length in ["", "abc", "-1", "5, 5", "99999999999999999999999"] {
+        let err = reader(content_length).err().expect(content_length);
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+    let mut body = String::new();
+    reader("5").unwrap().read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello");
+}
@@ -0,0 +1,75 @@
+// each test crate compiles its own copy and uses only some of these
+#![allow(dead_code)]
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use single_threaded_server::{
+    mock_stream::MockStream,
+    parser::{
+        first_line_parser::FirstLineRequestParser,
+        http_message_parser::{Parser, Request},
+    },
+    response::Response,
+    routing::RoutingMap,
+    server::ShutdownHandle,
+    task_manager::{ConnectionSettings, handle},
+};
+
+// parses a request the way a connection would, without running it
+pub fn request(routing: &Arc<RoutingMap>, raw_request: &str) -> Request {
+    let mut stream = MockStream::new(raw_request);
+    let payload = Parser::new(FirstLineRequestParser::default())
+        .parse(&mut stream)
+        .unwrap_or_else(|err| panic!("{err}"));
+    payload.from(Arc::clone(routing))
+}
+
+// calls the handler of the matching route directly
+pub fn respond(routing: &Arc<RoutingMap>, raw_request: &str) -> Response {
+    let request = request(routing, raw_request);
+    let handler = routing
+        .get_handler(&request.request_method(), request.request_path())
+        .expect("a route should match");
+    handler.call(request)
+}
+
+// serves one connection over a MockStream and returns everything written back
+pub fn exchange(routing: Arc<RoutingMap>, raw_request: &str) -> String {
+    let stream = MockStream::new(raw_request);
+    let received = stream.received_data_handle();
+    handle(
+        stream,
+        routing,
+        Arc::new(ConnectionSettings::default()),
+        ShutdownHandle::default(),
+    )
+    .unwrap();
+    received.lock().unwrap().clone()
+}
+
+// accepts a single connection on a real socket and serves it on another thread
+pub fn serve_one_connection(routing: Arc<RoutingMap>, settings: ConnectionSettings) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (connection, _) = listener.accept().unwrap();
+        handle(connection, routing, Arc::new(settings), ShutdownHandle::default()).unwrap();
+    });
+    (address, server)
+}
+
+// writes the requests at once and reads until the server closes the connection
+pub fn exchange_over_tcp(routing: Arc<RoutingMap>, settings: ConnectionSettings, raw_requests: &[u8]) -> String {
+    let (address, server) = serve_one_connection(routing, settings);
+    let mut client = TcpStream::connect(address).unwrap();
+    client.write_all(raw_requests).unwrap();
+    let mut responses = String::new();
+    client.read_to_string(&mut responses).unwrap();
+    server.join().unwrap();
+    responses
+}
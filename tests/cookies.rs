@@ -0,0 +1,71 @@
+use std::{
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use single_threaded_server::{
+    cookie::{Cookie, CookieJar, http_date},
+    routing::RoutingMap,
+};
+
+mod common;
+
+fn jar_from(cookie_header: &str) -> CookieJar {
+    let raw_request = format!("GET / HTTP/1.1\r\nHost: localhost\r\nCookie: {}\r\n\r\n", cookie_header);
+    CookieJar::from_request_headers(&common::request(&Arc::new(RoutingMap::new()), &raw_request))
+}
+
+#[test]
+fn values_cannot_inject_attributes_or_header_lines() {
+    let cookie = Cookie::new("theme", "dark; Domain=evil.test\r\nX-Injected: 1").path("/a;b");
+    let encoded = cookie.encode();
+    assert_eq!(
+        encoded,
+        "theme=dark%3B%20Domain=evil.test%0D%0AX-Injected:%201; Path=/a%3Bb"
+    );
+    assert!(!encoded.contains('\r') && !encoded.contains('\n'));
+    assert_eq!(Cookie::new("bad name=", "\"quoted\\\"").encode(), "bad%20name%3D=%22quoted%5C%22");
+}
+
+#[test]
+fn encoded_values_read_back_unchanged() {
+    let value = "a b;c,d\"e\\f%g é";
+    let encoded = Cookie::new("note", value).encode();
+    let jar = jar_from(&encoded);
+    assert_eq!(jar.get("note").unwrap().value(), value);
+}
+
+#[test]
+fn jar_skips_malformed_pairs() {
+    let jar = jar_from("first=1; junk; second=\"two\"; =; third=%zz");
+    assert_eq!(jar.get("first").unwrap().value(), "1");
+    assert_eq!(jar.get("second").unwrap().value(), "two");
+    // a stray percent sign that is no escape stays as sent
+    assert_eq!(jar.get("third").unwrap().value(), "%zz");
+    assert!(jar.get("junk").is_none());
+}
+
+#[test]
+fn jar_tracks_added_and_removed_cookies() {
+    let mut jar = jar_from("theme=dark; lang=en");
+    jar.add(Cookie::new("theme", "light"));
+    jar.remove("lang");
+    assert_eq!(jar.get("theme").unwrap().value(), "light");
+    assert!(jar.get("lang").is_none());
+    let set_cookies: Vec<String> = jar.delta().iter().map(Cookie::encode).collect();
+    assert_eq!(
+        set_cookies,
+        ["theme=light", "lang=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT"]
+    );
+}
+
+#[test]
+fn formats_imf_fixdates() {
+    let date = |seconds| http_date(UNIX_EPOCH + Duration::from_secs(seconds));
+    assert_eq!(date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+    assert_eq!(date(951_782_400), "Tue, 29 Feb 2000 00:00:00 GMT");
+    assert_eq!(date(1_709_210_096), "Thu, 29 Feb 2024 12:34:56 GMT");
+    // 2100 is not a leap year, the day after 28 Feb is 1 Mar
+    assert_eq!(date(4_107_542_400), "Mon, 01 Mar 2100 00:00:00 GMT");
+    assert_eq!(date(4_233_772_799), "Fri, 29 Feb 2104 23:59:59 GMT");
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::{Arc, mpsc},
+    time::Duration,
+};
+
+use single_threaded_server::{
+    response::{Html, StatusCode},
+    routing::{HttpVerb, RoutingMap},
+    task_manager::TaskManager,
+};
+
+mod common;
+use common::exchange;
+
+fn routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::GET, || -> Html { panic!("handler bug") }, "/panic")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("fine".to_string()), "/fine")
+        .unwrap();
+    routing
+}
+
+#[test]
+fn panicking_handler_is_a_500_and_the_worker_lives_on() {
+    let routing = Arc::new(routing());
+    let task_manager = TaskManager::new(1);
+    let (responses, received) = mpsc::channel();
+    for path in ["/panic", "/fine"] {
+        let routing = Arc::clone(&routing);
+        let responses = responses.clone();
+        // both requests share the single worker and the first one's connection stays usable
+        task_manager.execute(move || {
+            let raw_request = format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\nGET /fine HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                path
+            );
+            responses.send(exchange(routing, &raw_request)).unwrap();
+        });
+    }
+    let after_panic = received.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(after_panic.starts_with("HTTP/1.1 500 Internal Server Error"));
+    assert!(after_panic.ends_with("\r\n\r\nfine"));
+    let next_job = received.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(next_job.starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn custom_error_pages_render_404_and_405() {
+    let mut routing = routing();
+    routing.set_error_page(StatusCode::NotFound, |message: &str| {
+        Html::new(format!("<h1>{}</h1>", message))
+    });
+    routing.set_error_page(StatusCode::MethodNotAllowed, |_: &str| {
+        Html::new("<h1>try GET</h1>".to_string())
+    });
+    let routing = Arc::new(routing);
+    let missing = exchange(
+        Arc::clone(&routing),
+        "GET /missing HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(missing.starts_with("HTTP/1.1 404 Not Found"));
+    assert!(missing.ends_with("<h1>not found</h1>"));
+    let wrong_method = exchange(
+        routing,
+        "POST /fine HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(wrong_method.starts_with("HTTP/1.1 405 Method Not Allowed"));
+    assert!(wrong_method.contains("Allow: GET, HEAD, OPTIONS\r\n"));
+    assert!(wrong_method.ends_with("<h1>try GET</h1>"));
+}
+
+#[test]
+fn fallback_answers_unmatched_paths() {
+    let mut routing = routing();
+    routing.set_fallback(|| Html::new("fallback".to_string()));
+    let response = exchange(
+        Arc::new(routing),
+        "GET /anything/else HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("\r\n\r\nfallback"));
+}
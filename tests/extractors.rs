@@ -0,0 +1,175 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::{Value, json};
+
+use serde::Deserialize;
+use single_threaded_server::{
+    extractor::{BodyContentError, Json, Path, Query},
+    problem::Problem,
+    response::{Html, Response, StatusCode},
+    routing::{HttpVerb, RoutingMap},
+};
+
+mod common;
+
+#[derive(Deserialize)]
+struct Search {
+    term: String,
+}
+
+fn call(routing: RoutingMap, raw_request: &str) -> String {
+    String::from_utf8(respond(routing, raw_request).body().to_vec()).unwrap()
+}
+
+fn respond(routing: RoutingMap, raw_request: &str) -> Response {
+    common::respond(&Arc::new(routing), raw_request)
+}
+
+fn search_routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::GET,
+            |search: Option<Query<Search>>| match search {
+                Some(Query(search)) => Html::new(search.term),
+                None => Html::new("no term".to_string()),
+            },
+            "/search",
+        )
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, |Query(search): Query<Search>| Html::new(search.term), "/strict")
+        .unwrap();
+    routing
+}
+
+#[test]
+fn missing_query_becomes_none() {
+    let with_term = "GET /search?term=rust HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let without_term = "GET /search HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let strict = "GET /strict?term=http HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    assert_eq!(call(search_routing(), with_term), "rust");
+    assert_eq!(call(search_routing(), without_term), "no term");
+    assert_eq!(call(search_routing(), strict), "http");
+}
+
+fn stats_routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |search: Option<Query<Search>>, body: Result<Json<HashMap<String, u32>>, BodyContentError>| {
+                let term = search.map_or("none".to_string(), |Query(search)| search.term);
+                match body {
+                    Ok(Json(counts)) => Html::new(format!("{} {}", term, counts["views"])),
+                    Err(err) => Html::new(format!("{} rejected: {}", term, err)),
+                }
+            },
+            "/stats",
+        )
+        .unwrap();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |body: Option<Json<Search>>| Html::new(body.map_or("empty".to_string(), |Json(search)| search.term)),
+            "/optional",
+        )
+        .unwrap();
+    routing
+}
+
+#[test]
+fn body_rejection_reaches_the_handler() {
+    let valid = "POST /stats?term=a HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 13\r\n\r\n{\"views\": 42}";
+    let wrong_type = "POST /stats HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}";
+    let optional = "POST /optional HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}";
+    assert_eq!(call(stats_routing(), valid), "a 42");
+    assert_eq!(call(stats_routing(), wrong_type), "none rejected: content type header mismatch");
+    assert_eq!(call(stats_routing(), optional), "empty");
+}
+
+#[derive(Deserialize)]
+struct Signup {
+    #[allow(dead_code)]
+    profile: Profile,
+}
+
+#[derive(Deserialize)]
+struct Profile {
+    #[allow(dead_code)]
+    age: u8,
+}
+
+fn signup_routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::POST, |_: Json<Signup>| Html::new("ok".to_string()), "/signup")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, |_: Query<Search>| Html::new("ok".to_string()), "/search")
+        .unwrap();
+    routing
+}
+
+const BAD_SIGNUP: &str = "POST /signup HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 27\r\n\r\n{\"profile\": {\"age\": \"old\"}}";
+
+#[test]
+fn rejections_are_problem_details() {
+    let response = respond(signup_routing(), BAD_SIGNUP);
+    assert_eq!(response.status_code(), &StatusCode::BadRequest);
+    assert_eq!(response.headers().get("content-type"), Some("application/problem+json"));
+    let problem: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(problem["type"], "about:blank");
+    assert_eq!(problem["title"], "Bad Request");
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["field"], "profile.age");
+    assert_eq!(problem["expected"], "u8");
+    assert_eq!(problem["line"], 1);
+    assert_eq!(problem["column"], 25);
+
+    let missing = respond(signup_routing(), "GET /search?page=2 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let problem: Value = serde_json::from_slice(missing.body()).unwrap();
+    assert_eq!(problem["field"], "term");
+    assert_eq!(problem["detail"], "invalid query string: missing field `term`");
+}
+
+#[test]
+fn rejection_renderer_replaces_the_default_body() {
+    let mut routing = signup_routing();
+    routing.set_rejection_renderer(|problem: &Problem| {
+        Json(json!({ "error": problem.detail, "field": problem.members.get("field") }))
+    });
+    let response = respond(routing, BAD_SIGNUP);
+    let body: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(response.headers().get("content-type"), Some("application/json"));
+    assert_eq!(body["field"], "profile.age");
+    assert!(body["error"].as_str().unwrap().starts_with("invalid json body: invalid type: string"));
+}
+
+#[test]
+fn path_outside_any_route_is_not_found() {
+    let mut routing = RoutingMap::new();
+    routing.set_fallback(|Path(id): Path<u32>| Html::new(id.to_string()));
+    let routing = Arc::new(routing);
+    let request = common::request(&routing, "GET /users/7 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let response = routing.fallback().unwrap().call(request);
+    // no route matched, so there are no path parameters to extract
+    assert_eq!(response.status_code(), &StatusCode::NotFound);
+    let problem: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(problem["status"], 404);
+    assert_eq!(problem["title"], "Not Found");
+}
+
+#[test]
+fn reason_phrases_match_the_status_line() {
+    for status_code in [
+        StatusCode::Ok,
+        StatusCode::NotFound,
+        StatusCode::PayloadTooLarge,
+        StatusCode::UnprocessableEntity,
+        StatusCode::RequestHeaderFieldsTooLarge,
+    ] {
+        let status_line = format!("HTTP/1.1 {} {}\r\n", status_code.as_u16(), status_code.reason_phrase());
+        assert_eq!(status_code.status_line(), status_line);
+    }
+}
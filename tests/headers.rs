@@ -0,0 +1,123 @@
+use single_threaded_server::headers::{
+    Accept, Authorization, Header, HeaderMap, Host, IfNoneMatch, MediaRange,
+};
+
+#[test]
+fn decodes_authorization_schemes() {
+    assert_eq!(
+        Authorization::decode(&["Basic YWxhZGRpbjpvcGVuOnNlc2FtZQ=="]),
+        Some(Authorization::Basic {
+            username: "aladdin".to_string(),
+            // only the first colon separates the user from the password
+            password: "open:sesame".to_string(),
+        })
+    );
+    assert_eq!(
+        Authorization::decode(&["bearer  abc.def"]),
+        Some(Authorization::Bearer("abc.def".to_string()))
+    );
+    assert_eq!(
+        Authorization::decode(&["Digest realm=x"]),
+        Some(Authorization::Other {
+            scheme: "Digest".to_string(),
+            credentials: "realm=x".to_string(),
+        })
+    );
+    let basic = Authorization::Basic {
+        username: "a".to_string(),
+        password: "b".to_string(),
+    };
+    assert_eq!(Authorization::decode(&[basic.encode().as_str()]), Some(basic));
+}
+
+#[test]
+fn rejects_malformed_authorization() {
+    assert_eq!(Authorization::decode(&["Bearer"]), None);
+    assert_eq!(Authorization::decode(&["Basic not base64!"]), None);
+    // "user" without a colon
+    assert_eq!(Authorization::decode(&["Basic dXNlcg=="]), None);
+    // invalid utf-8 once decoded
+    assert_eq!(Authorization::decode(&["Basic //79"]), None);
+    assert_eq!(Authorization::decode(&["Bearer a", "Bearer b"]), None);
+}
+
+#[test]
+fn orders_accept_by_quality() {
+    let accept = Accept::decode(&["text/html;q=0.5, application/json", "Text/*; Q=0.8, image/png;q=0"]).unwrap();
+    let ranges: Vec<(&str, f32)> = accept
+        .0
+        .iter()
+        .map(|MediaRange { media_type, quality }| (media_type.as_str(), *quality))
+        .collect();
+    assert_eq!(
+        ranges,
+        [("application/json", 1.0), ("text/*", 0.8), ("text/html", 0.5), ("image/png", 0.0)]
+    );
+    assert!(accept.accepts("text/plain"));
+    assert!(accept.accepts("APPLICATION/JSON"));
+    // q=0 means not acceptable
+    assert!(!accept.accepts("image/png"));
+    assert!(!accept.accepts("image/gif"));
+}
+
+#[test]
+fn rejects_malformed_accept() {
+    assert_eq!(Accept::decode(&["html"]), None);
+    assert_eq!(Accept::decode(&["text/html;q=high"]), None);
+    assert_eq!(Accept::decode(&["text/html;q=1.5"]), None);
+    assert_eq!(Accept::decode(&["text/html;q=-1"]), None);
+}
+
+#[test]
+fn decodes_if_none_match() {
+    assert_eq!(IfNoneMatch::decode(&["*"]), Some(IfNoneMatch::Any));
+    let tags = IfNoneMatch::decode(&["\"a\", W/\"b\"", "\"c\""]).unwrap();
+    assert_eq!(
+        tags,
+        IfNoneMatch::Tags(vec!["\"a\"".to_string(), "W/\"b\"".to_string(), "\"c\"".to_string()])
+    );
+    // weak comparison ignores the W/ prefix on either side
+    assert!(tags.matches("W/\"a\""));
+    assert!(tags.matches("\"b\""));
+    assert!(!tags.matches("\"d\""));
+}
+
+#[test]
+fn rejects_malformed_if_none_match() {
+    assert_eq!(IfNoneMatch::decode(&["abc"]), None);
+    assert_eq!(IfNoneMatch::decode(&["\""]), None);
+    assert_eq!(IfNoneMatch::decode(&["abc\""]), None);
+    assert_eq!(IfNoneMatch::decode(&["\"a\", b"]), None);
+    assert_eq!(IfNoneMatch::decode(&[" , "]), None);
+}
+
+#[test]
+fn decodes_host_with_and_without_port() {
+    let host = |hostname: &str, port| Host {
+        hostname: hostname.to_string(),
+        port,
+    };
+    assert_eq!(Host::decode(&["example.com"]), Some(host("example.com", None)));
+    assert_eq!(Host::decode(&["example.com:8080"]), Some(host("example.com", Some(8080))));
+    assert_eq!(Host::decode(&["[::1]"]), Some(host("[::1]", None)));
+    assert_eq!(Host::decode(&["[::1]:443"]), Some(host("[::1]", Some(443))));
+}
+
+#[test]
+fn rejects_malformed_host() {
+    assert_eq!(Host::decode(&["example.com:http"]), None);
+    assert_eq!(Host::decode(&["example.com:"]), None);
+    assert_eq!(Host::decode(&["example.com:70000"]), None);
+    assert_eq!(Host::decode(&[":80"]), None);
+    assert_eq!(Host::decode(&["a.com", "b.com"]), None);
+}
+
+#[test]
+fn typed_get_collects_every_field_line() {
+    let headers: HeaderMap = [("accept", "text/html"), ("Accept", "application/json;q=0.9")]
+        .into_iter()
+        .collect();
+    let accept = headers.typed_get::<Accept>().unwrap();
+    assert_eq!(accept.0.len(), 2);
+    assert_eq!(headers.typed_get::<Host>(), None);
+}
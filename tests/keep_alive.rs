@@ -0,0 +1,89 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use single_threaded_server::{
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+    task_manager::ConnectionSettings,
+};
+
+mod common;
+
+fn routing() -> Arc<RoutingMap> {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("hello".to_string()), "/")
+        .unwrap();
+    Arc::new(routing)
+}
+
+fn responses(settings: ConnectionSettings, raw_requests: &str) -> Vec<String> {
+    common::exchange_over_tcp(routing(), settings, raw_requests.as_bytes())
+        .split("HTTP/1.1 ")
+        .skip(1)
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn http_1_0_closes_unless_asked_to_keep_alive() {
+    let answered = responses(
+        ConnectionSettings::default(),
+        "GET / HTTP/1.0\r\n\r\nGET / HTTP/1.0\r\n\r\n",
+    );
+    assert_eq!(answered.len(), 1);
+    assert!(answered[0].contains("Connection: close\r\n"));
+
+    let answered = responses(
+        ConnectionSettings::default(),
+        "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET / HTTP/1.0\r\n\r\n",
+    );
+    assert_eq!(answered.len(), 2);
+    assert!(answered[0].contains("Connection: keep-alive\r\n"));
+}
+
+#[test]
+fn connection_close_is_honoured() {
+    let answered = responses(
+        ConnectionSettings::default(),
+        "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Close\r\n\r\n\
+         GET / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert_eq!(answered.len(), 2);
+    assert!(answered[0].contains("Connection: keep-alive\r\n"));
+    assert!(answered[1].contains("Connection: close\r\n"));
+}
+
+#[test]
+fn connection_closes_after_the_request_limit() {
+    let mut settings = ConnectionSettings::default();
+    settings.set_max_requests_per_connection(2);
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    let answered = responses(settings, &request.repeat(3));
+    assert_eq!(answered.len(), 2);
+    assert!(answered[0].contains("Connection: keep-alive\r\n"));
+    assert!(answered[1].contains("Connection: close\r\n"));
+}
+
+#[test]
+fn idle_connection_closes_after_the_keep_alive_timeout() {
+    let mut settings = ConnectionSettings::default();
+    settings.set_keep_alive_timeout(Duration::from_millis(200));
+    let (address, server) = common::serve_one_connection(routing(), settings);
+    let mut client = TcpStream::connect(address).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let started = Instant::now();
+    // the client stays silent after its request, only the server can end this read
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+    assert!(response.contains("Connection: keep-alive\r\n"));
+    assert!(response.ends_with("\r\n\r\nhello"));
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
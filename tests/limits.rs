@@ -0,0 +1,117 @@
+use single_threaded_server::{
+    mock_stream::MockStream,
+    parser::{
+        first_line_parser::FirstLineRequestParser,
+        http_message_parser::{MessageParseError, Parser, ParserLimits},
+    },
+    response::StatusCode,
+};
+
+fn limits() -> ParserLimits {
+    ParserLimits {
+        max_request_line_length: 32,
+        max_header_bytes: 64,
+        max_header_count: 3,
+        max_body_size: 8,
+    }
+}
+
+fn parse_error(raw_request: &str) -> MessageParseError {
+    let mut stream = MockStream::new(raw_request);
+    match Parser::new(FirstLineRequestParser::default())
+        .with_limits(limits())
+        .parse(&mut stream)
+    {
+        Ok(_) => panic!("the request should be rejected"),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn request_within_the_limits_parses() {
+    let mut stream = MockStream::new(
+        "POST /short HTTP/1.1\r\nHost: localhost\r\nContent-Length: 8\r\n\r\n12345678",
+    );
+    let mut payload = Parser::new(FirstLineRequestParser::default())
+        .with_limits(limits())
+        .parse(&mut stream)
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert_eq!(payload.take_leftover(), Some(Vec::new()));
+}
+
+#[test]
+fn long_request_line_is_414() {
+    let err = parse_error(&format!("GET /{} HTTP/1.1\r\nHost: localhost\r\n\r\n", "a".repeat(40)));
+    assert_eq!(err, MessageParseError::UriTooLong);
+    assert_eq!(err.status_code(), StatusCode::UriTooLong);
+}
+
+#[test]
+fn long_request_line_is_414_before_it_ends() {
+    // no CRLF has arrived yet, the parser must not wait for one
+    let err = parse_error(&format!("GET /{}", "a".repeat(40)));
+    assert_eq!(err, MessageParseError::UriTooLong);
+}
+
+#[test]
+fn too_many_header_bytes_is_431() {
+    let err = parse_error(&format!("GET / HTTP/1.1\r\nHost: localhost\r\nX-Big: {}\r\n\r\n", "b".repeat(64)));
+    assert_eq!(err, MessageParseError::HeadersTooLarge);
+    assert_eq!(err.status_code(), StatusCode::RequestHeaderFieldsTooLarge);
+}
+
+#[test]
+fn too_many_headers_is_431() {
+    let err = parse_error("GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\nD: 4\r\n\r\n");
+    assert_eq!(err, MessageParseError::HeadersTooLarge);
+}
+
+#[test]
+fn oversized_content_length_is_413_before_the_body_is_read() {
+    // the body never arrives, reading it would end in a closed connection instead
+    let err = parse_error("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1000000\r\n\r\n");
+    assert_eq!(err, MessageParseError::PayloadTooLarge);
+    assert_eq!(err.status_code(), StatusCode::PayloadTooLarge);
+}
+
+#[test]
+fn chunked_body_over_the_limit_is_413_mid_stream() {
+    // the second chunk size alone pushes the body past 8 bytes, its data is never sent
+    let err = parse_error(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n5\r\n",
+    );
+    assert_eq!(err, MessageParseError::PayloadTooLarge);
+}
+
+#[test]
+fn huge_chunk_size_is_413_without_overflowing() {
+    for size in ["ffffffffffffffff", "fffffffffffffffe"] {
+        let err = parse_error(&format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n{}\r\nmore",
+            size
+        ));
+        assert_eq!(err, MessageParseError::PayloadTooLarge, "{size}");
+    }
+}
+
+#[test]
+fn differing_content_lengths_are_400() {
+    let err = parse_error("POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 7\r\n\r\nhiGET / HTTP/1.1\r\n\r\n");
+    assert_eq!(err, MessageParseError::Malformed("conflicting content length headers".to_string()));
+    assert_eq!(err.status_code(), StatusCode::BadRequest);
+    // the same length repeated is unambiguous
+    let mut stream = MockStream::new("POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nhi");
+    assert!(Parser::new(FirstLineRequestParser::default()).parse(&mut stream).is_ok());
+}
+
+#[test]
+fn content_length_with_transfer_encoding_is_400() {
+    let err = parse_error(
+        "POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+    );
+    assert_eq!(
+        err,
+        MessageParseError::Malformed("content length sent together with transfer encoding".to_string())
+    );
+    assert_eq!(err.status_code(), StatusCode::BadRequest);
+}
@@ -0,0 +1,79 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+};
+
+use single_threaded_server::{response::Html, server::Server};
+
+fn get(address: SocketAddr) -> String {
+    let mut client = TcpStream::connect(address).unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn serve_on(server: Server, addresses: Vec<SocketAddr>) {
+    let shutdown = server.shutdown_handle();
+    let server_thread = thread::spawn(move || server.listen());
+    for address in addresses {
+        let response = get(address);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{address}");
+        assert!(response.ends_with("hello"), "{address}");
+    }
+    shutdown.shutdown();
+    server_thread.join().unwrap().unwrap();
+}
+
+fn server_on(addresses: &[&str]) -> Server {
+    let mut builder = Server::builder().threads(2);
+    for address in addresses {
+        builder = builder.bind(*address).unwrap();
+    }
+    let mut server = builder.build().unwrap();
+    server.get("/", || Html::new("hello".to_string())).unwrap();
+    server
+}
+
+fn ipv6_available() -> bool {
+    TcpListener::bind("[::1]:0").is_ok()
+}
+
+#[test]
+fn serves_a_request_on_each_bound_address() {
+    let server = server_on(&["127.0.0.1:0", "127.0.0.1:0"]);
+    let addresses = server.local_addrs();
+    assert_eq!(addresses.len(), 2);
+    assert_ne!(addresses[0], addresses[1]);
+    serve_on(server, addresses);
+}
+
+#[test]
+fn serves_ipv4_and_ipv6_side_by_side() {
+    if !ipv6_available() {
+        return;
+    }
+    let server = server_on(&["127.0.0.1:0", "[::1]:0"]);
+    let addresses = server.local_addrs();
+    assert!(addresses[1].is_ipv6());
+    serve_on(server, addresses);
+}
+
+#[test]
+fn ipv6_wildcard_shares_a_port_with_ipv4_wildcard() {
+    if !ipv6_available() {
+        return;
+    }
+    // a free port, released again so both listeners can take it
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let ipv4 = format!("0.0.0.0:{}", port);
+    let ipv6 = format!("[::]:{}", port);
+    // without only_v6 the `[::]` listener would already hold the IPv4 port
+    let server = server_on(&[&ipv4, &ipv6]);
+    assert_eq!(server.local_addrs().len(), 2);
+    let loopbacks = [format!("127.0.0.1:{}", port), format!("[::1]:{}", port)];
+    serve_on(server, loopbacks.iter().map(|address| address.parse().unwrap()).collect());
+}
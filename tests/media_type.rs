@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use single_threaded_server::{
+    extractor::{Form, Json},
+    media_type::MediaType,
+    response::{Html, Response, StatusCode},
+    routing::{HttpVerb, RoutingMap},
+};
+
+mod common;
+
+#[derive(Deserialize)]
+struct Greeting {
+    name: String,
+}
+
+fn post(content_type: &str, body: &str) -> Response {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::POST, |Json(greeting): Json<Greeting>| Html::new(greeting.name), "/json")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::POST, |Form(greeting): Form<Greeting>| Html::new(greeting.name), "/form")
+        .unwrap();
+    let path = if content_type.to_lowercase().contains("json") { "/json" } else { "/form" };
+    let raw_request = format!(
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        path,
+        content_type,
+        body.len(),
+        body
+    );
+    common::respond(&Arc::new(routing), &raw_request)
+}
+
+fn body(response: Response) -> String {
+    assert_eq!(response.status_code(), &StatusCode::Ok);
+    String::from_utf8(response.body().to_vec()).unwrap()
+}
+
+#[test]
+fn json_accepts_charset_and_structured_suffixes() {
+    let greeting = r#"{"name":"ada"}"#;
+    assert_eq!(body(post("application/json", greeting)), "ada");
+    assert_eq!(body(post("application/json; charset=utf-8", greeting)), "ada");
+    assert_eq!(body(post("Application/JSON; Charset=\"UTF-8\"", greeting)), "ada");
+    assert_eq!(body(post("application/vnd.x+json", greeting)), "ada");
+}
+
+#[test]
+fn json_rejects_other_media_types_and_charsets() {
+    let greeting = r#"{"name":"ada"}"#;
+    for content_type in ["text/plain", "application/json; charset=latin1", "application/jsonx"] {
+        let response = post(content_type, greeting);
+        assert_eq!(response.status_code(), &StatusCode::UnsupportedMediaType, "{content_type}");
+    }
+}
+
+#[test]
+fn forms_decode_the_declared_charset() {
+    assert_eq!(body(post("application/x-www-form-urlencoded", "name=Ren%C3%A9e")), "Renée");
+    // é is the single byte e9 in windows-1252
+    assert_eq!(
+        body(post("application/x-www-form-urlencoded; charset=windows-1252", "name=Ren%E9e")),
+        "Renée"
+    );
+    assert_eq!(
+        body(post("application/x-www-form-urlencoded; charset=\"ISO-8859-1\"", "name=Ren%E9e+%80")),
+        "Renée €"
+    );
+    let response = post("application/x-www-form-urlencoded; charset=klingon", "name=x");
+    assert_eq!(response.status_code(), &StatusCode::UnsupportedMediaType);
+}
+
+#[test]
+fn parses_mixed_case_and_quoted_parameters() {
+    let media_type = MediaType::parse("Multipart/Form-Data; Boundary=\"a;b \\\"c\\\"\" ; CHARSET=utf-8").unwrap();
+    assert!(media_type.is("multipart", "form-data"));
+    assert_eq!(media_type.essence(), "multipart/form-data");
+    assert_eq!(media_type.parameter("boundary"), Some("a;b \"c\""));
+    assert_eq!(media_type.charset(), Some("utf-8"));
+    assert_eq!(media_type.encoding(), Some(encoding_rs::UTF_8));
+
+    let problem = MediaType::parse("application/problem+json").unwrap();
+    assert_eq!(problem.suffix(), Some("json"));
+    assert!(problem.is_json());
+    assert_eq!(MediaType::parse("json"), None);
+    assert_eq!(MediaType::parse("text/pl ain"), None);
+}
@@ -0,0 +1,188 @@
+use std::{
+    fs,
+    io::{self, Read},
+    sync::Arc,
+};
+
+use single_threaded_server::{
+    multipart::{FieldData, Multipart, MultipartError, MultipartLimits},
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+};
+
+mod common;
+
+const BODY: &[u8] = b"preamble\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+hello\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"upload\"; filename=\"a;b.txt\"\r\n\
+Content-Type: text/plain\r\n\r\n\
+line one\r\nline two\r\n\
+--XyZ--\r\n\
+epilogue";
+
+#[test]
+fn parses_fields_and_files() {
+    let multipart = Multipart::parse(BODY, "XyZ", MultipartLimits::default()).unwrap();
+    assert_eq!(multipart.fields().len(), 2);
+    assert_eq!(multipart.field("title").unwrap().text().unwrap(), "hello");
+    let upload = multipart.field("upload").unwrap();
+    assert_eq!(upload.filename(), Some("a;b.txt"));
+    assert_eq!(upload.content_type(), Some("text/plain"));
+    assert_eq!(upload.text().unwrap(), "line one\r\nline two");
+}
+
+#[test]
+fn spills_large_parts_to_temp_files() {
+    let limits = MultipartLimits {
+        spill_threshold: 8,
+        ..MultipartLimits::default()
+    };
+    let multipart = Multipart::parse(BODY, "XyZ", limits).unwrap();
+    let FieldData::File(file) = multipart.field("upload").unwrap().data() else {
+        panic!("large part should be on disk");
+    };
+    let path = file.path().to_path_buf();
+    assert!(path.exists());
+    assert!(matches!(multipart.field("title").unwrap().data(), FieldData::Memory(_)));
+    drop(multipart);
+    assert!(!path.exists());
+}
+
+#[test]
+fn enforces_part_limits() {
+    let limits = MultipartLimits {
+        max_part_size: 8,
+        ..MultipartLimits::default()
+    };
+    let err = Multipart::parse(BODY, "XyZ", limits).err().unwrap();
+    assert!(matches!(err, MultipartError::PartTooLarge(name) if name == "upload"));
+    let limits = MultipartLimits {
+        max_parts: 1,
+        ..MultipartLimits::default()
+    };
+    let err = Multipart::parse(BODY, "XyZ", limits).err().unwrap();
+    assert!(matches!(err, MultipartError::TooManyParts));
+}
+
+// hands the body out a few bytes at a time, like a slow client
+struct Trickle<'a> {
+    body: &'a [u8],
+    chunk: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.chunk.min(buf.len()).min(self.body.len());
+        buf[..n].copy_from_slice(&self.body[..n]);
+        self.body = &self.body[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn boundaries_split_across_reads_are_found() {
+    for chunk in [1, 3, 7] {
+        let limits = MultipartLimits {
+            spill_threshold: 8,
+            ..MultipartLimits::default()
+        };
+        let multipart = Multipart::parse(Trickle { body: BODY, chunk }, "XyZ", limits).unwrap();
+        assert_eq!(multipart.field("title").unwrap().text().unwrap(), "hello");
+        let upload = multipart.field("upload").unwrap();
+        assert_eq!(upload.text().unwrap(), "line one\r\nline two");
+        assert_eq!(upload.size(), 18);
+    }
+}
+
+fn spilled_upload_sizes() -> Vec<u64> {
+    fs::read_dir(std::env::temp_dir())
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("upload-"))
+        .filter_map(|entry| Some(entry.metadata().ok()?.len()))
+        .collect()
+}
+
+// checks the temp file before the parser may see the end of the part
+struct WatchedUpload {
+    body: Vec<u8>,
+    position: usize,
+    part_end: usize,
+    part_size: u64,
+}
+
+impl Read for WatchedUpload {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.part_end {
+            // all but the last read's worth of the part is on disk already
+            let window = 2 * 8192;
+            assert!(
+                spilled_upload_sizes()
+                    .into_iter()
+                    .any(|size| size + window >= self.part_size && size < self.part_size),
+                "the part should be written out while it arrives"
+            );
+        }
+        // never read past the end of the part in one go
+        let end = if self.position < self.part_end { self.part_end } else { self.body.len() };
+        let n = buf.len().min(4096).min(end - self.position);
+        buf[..n].copy_from_slice(&self.body[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn large_parts_are_streamed_to_disk() {
+    let part_size = 1024 * 1024;
+    let mut body = b"--XyZ\r\nContent-Disposition: form-data; name=\"video\"; filename=\"a.bin\"\r\n\r\n".to_vec();
+    body.extend((0..part_size).map(|i| (i % 251) as u8));
+    let part_end = body.len();
+    body.extend_from_slice(b"\r\n--XyZ--\r\n");
+    let upload = WatchedUpload {
+        body,
+        position: 0,
+        part_end,
+        part_size: part_size as u64,
+    };
+    let limits = MultipartLimits {
+        spill_threshold: 64 * 1024,
+        ..MultipartLimits::default()
+    };
+    let multipart = Multipart::parse(upload, "XyZ", limits).unwrap();
+    let video = multipart.field("video").unwrap();
+    assert!(matches!(video.data(), FieldData::File(_)));
+    assert_eq!(video.size(), part_size);
+    let bytes = video.bytes().unwrap();
+    assert!(bytes.iter().enumerate().all(|(i, byte)| *byte == (i % 251) as u8));
+}
+
+#[test]
+fn extractor_reads_the_body_off_the_connection() {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |multipart: Multipart| {
+                let names: Vec<&str> = multipart.fields().iter().map(|field| field.name()).collect();
+                Html::new(names.join(","))
+            },
+            "/upload",
+        )
+        .unwrap();
+    let mut raw_request = format!(
+        "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Type: multipart/form-data; boundary=XyZ\r\n\
+         Content-Length: {}\r\n\r\n",
+        BODY.len()
+    )
+    .into_bytes();
+    raw_request.extend_from_slice(BODY);
+    raw_request.extend_from_slice(b"GET /missing HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let responses = common::exchange(Arc::new(routing), &String::from_utf8(raw_request).unwrap());
+    assert!(responses.contains("\r\n\r\ntitle,upload"), "{responses}");
+    // the epilogue was drained, the next request starts where the body ended
+    assert!(responses.contains("HTTP/1.1 404 Not Found"), "{responses}");
+}
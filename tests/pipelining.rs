@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use single_threaded_server::{
+    mock_stream::MockStream,
+    parser::{
+        first_line_parser::FirstLineRequestParser,
+        http_message_parser::{Parser, Request},
+    },
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+    task_manager::ConnectionSettings,
+};
+
+mod common;
+
+fn parse_pipelined(stream: &mut MockStream, count: usize) -> Vec<Request> {
+    let routing = Arc::new(RoutingMap::new());
+    let mut buffered_data = Vec::new();
+    let mut requests = Vec::new();
+    for _ in 0..count {
+        let parser = Parser::with_buffered_data(FirstLineRequestParser::default(), buffered_data);
+        let mut payload = parser.parse(stream).unwrap_or_else(|err| panic!("{err}"));
+        buffered_data = payload.take_leftover().expect("message end should be known");
+        requests.push(payload.from(Arc::clone(&routing)));
+    }
+    assert!(buffered_data.is_empty());
+    requests
+}
+
+#[test]
+fn parses_pipelined_requests_without_bodies() {
+    let mut stream = MockStream::new(
+        "GET /first HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         GET /second HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         GET /third HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    let requests = parse_pipelined(&mut stream, 3);
+    let paths: Vec<&str> = requests.iter().map(|request| request.request_path()).collect();
+    assert_eq!(paths, ["/first", "/second", "/third"]);
+}
+
+#[test]
+fn parses_pipelined_requests_after_content_length_body() {
+    let mut stream = MockStream::new(
+        "POST /users HTTP/1.1\r\nHost: localhost\r\nContent-Length: 11\r\n\r\nhello world\
+         GET /users HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    let requests = parse_pipelined(&mut stream, 2);
+    assert_eq!(requests[0].body(), b"hello world");
+    assert_eq!(requests[1].request_path(), "/users");
+    assert!(requests[1].body().is_empty());
+}
+
+#[test]
+fn parses_pipelined_requests_after_chunked_body() {
+    let mut stream = MockStream::new(
+        "POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+         5\r\nhel\r\n\r\n6\r\n world\r\n0\r\n\r\n\
+         POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+         3\r\nabc\r\n0\r\nChecksum: 1\r\n\r\n\
+         GET /done HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    let requests = parse_pipelined(&mut stream, 3);
+    assert_eq!(requests[0].body(), b"hel\r\n world");
+    assert_eq!(requests[1].body(), b"abc");
+    assert_eq!(requests[2].request_path(), "/done");
+}
+
+#[test]
+fn answers_pipelined_requests_in_order() {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("first".to_string()), "/first")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("second".to_string()), "/second")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("third".to_string()), "/third")
+        .unwrap();
+    let responses = common::exchange_over_tcp(
+        Arc::new(routing),
+        ConnectionSettings::default(),
+        b"GET /first HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          GET /second HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          GET /third HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    let first = responses.find("\r\n\r\nfirst").unwrap();
+    let second = responses.find("\r\n\r\nsecond").unwrap();
+    let third = responses.find("\r\n\r\nthird").unwrap();
+    assert!(first < second && second < third);
+    assert_eq!(responses.matches("HTTP/1.1 200 OK").count(), 3);
+}
@@ -0,0 +1,38 @@
+use single_threaded_server::{
+    mock_stream::MockStream,
+    parser::first_line_parser::FirstLineResponseParser,
+    proxy::{ProxyParser, ResponsePartProxySender},
+};
+
+#[test]
+fn proxies_repeated_headers_as_separate_field_lines() {
+    let mut upstream = MockStream::new(
+        "HTTP/1.1 200 OK\r\n\
+         Set-Cookie: session=abc; Path=/; Expires=Wed, 21 Oct 2026 07:28:00 GMT\r\n\
+         Set-Cookie: theme=dark\r\n\
+         X-Request-Id: 42\r\n\
+         Content-Length: 2\r\n\r\nok",
+    );
+    let mut client = Vec::new();
+    let mut response_parser = ProxyParser::new(
+        FirstLineResponseParser::default(),
+        &mut client,
+        ResponsePartProxySender {},
+    );
+    response_parser.parse(&mut upstream).unwrap();
+
+    let proxied = String::from_utf8(client).unwrap();
+    let set_cookie_lines: Vec<&str> = proxied
+        .lines()
+        .filter(|line| line.starts_with("Set-Cookie: "))
+        .collect();
+    assert_eq!(
+        set_cookie_lines,
+        [
+            "Set-Cookie: session=abc; Path=/; Expires=Wed, 21 Oct 2026 07:28:00 GMT",
+            "Set-Cookie: theme=dark",
+        ]
+    );
+    assert!(proxied.contains("\r\nX-Request-Id: 42\r\n"));
+    assert!(proxied.ends_with("\r\n\r\nok"));
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use single_threaded_server::{
+    extractor::Path,
+    handler::Service,
+    parser::http_message_parser::Request,
+    response::{Html, Response},
+    router::{Middleware, Router, join_paths},
+    routing::RoutingMap,
+};
+
+mod common;
+
+fn respond(routing: &Arc<RoutingMap>, method: &str, path: &str) -> Option<Response> {
+    let raw_request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path);
+    let request = common::request(routing, &raw_request);
+    let handler = routing.get_handler(&request.request_method(), request.request_path())?;
+    Some(handler.call(request))
+}
+
+fn body(response: Option<Response>) -> String {
+    String::from_utf8(response.expect("a route should match").body().to_vec()).unwrap()
+}
+
+#[derive(Deserialize)]
+struct PostParams {
+    user_id: u32,
+    post_id: u32,
+}
+
+#[test]
+fn nested_routes_are_mounted_under_the_prefix() {
+    let posts = Router::new()
+        .get("/", || Html::new("all posts".to_string()))
+        .get("/{post_id}", |Path(params): Path<PostParams>| {
+            Html::new(format!("post {} of user {}", params.post_id, params.user_id))
+        });
+    let mut routing = RoutingMap::new();
+    routing.nest("/users/{user_id}/posts/", posts).unwrap();
+    let routing = Arc::new(routing);
+    // params come from both the prefix and the nested route
+    assert_eq!(body(respond(&routing, "GET", "/users/7/posts/42")), "post 42 of user 7");
+    assert_eq!(body(respond(&routing, "GET", "/users/7/posts")), "all posts");
+    assert!(respond(&routing, "GET", "/posts/42").is_none());
+}
+
+#[test]
+fn joins_prefix_and_path_with_a_single_slash() {
+    assert_eq!(join_paths("/api", "/users"), "/api/users");
+    assert_eq!(join_paths("/api/", "users"), "/api/users");
+    assert_eq!(join_paths("/api", "/"), "/api");
+    assert_eq!(join_paths("", "/"), "/");
+    assert_eq!(join_paths("/", ""), "/");
+}
+
+#[test]
+fn merge_keeps_both_routers_and_reports_conflicts() {
+    let users = Router::new().get("/users", || Html::new("users".to_string()));
+    let health = Router::new().get("/health", || Html::new("healthy".to_string()));
+    let mut routing = RoutingMap::new();
+    routing.merge(users.merge(health)).unwrap();
+    let routing = Arc::new(routing);
+    assert_eq!(body(respond(&routing, "GET", "/users")), "users");
+    assert_eq!(body(respond(&routing, "GET", "/health")), "healthy");
+
+    let mut routing = RoutingMap::new();
+    routing
+        .merge(Router::new().get("/users", || Html::new("first".to_string())))
+        .unwrap();
+    let conflict = routing.merge(Router::new().get("/users", || Html::new("second".to_string())));
+    assert!(matches!(conflict, Err(matchit::InsertError::Conflict { .. })));
+    // the same path under another method is not a conflict
+    routing
+        .merge(Router::new().post("/users", || Html::new("created".to_string())))
+        .unwrap();
+}
+
+fn trace(name: &'static str) -> impl Middleware {
+    move |request: Request, next: &dyn Service| {
+        let mut response = next.call(request);
+        let trace = match response.headers().get("X-Trace") {
+            Some(inner) => format!("{} {}", inner, name),
+            None => name.to_string(),
+        };
+        response.set_header("X-Trace", &trace);
+        response
+    }
+}
+
+#[test]
+fn later_layers_wrap_earlier_ones() {
+    let router = Router::new()
+        .get("/wrapped", || Html::new("wrapped".to_string()))
+        .layer(trace("inner"))
+        .layer(trace("outer"))
+        .get("/plain", || Html::new("plain".to_string()));
+    let mut routing = RoutingMap::new();
+    routing.nest("/api", router).unwrap();
+    let routing = Arc::new(routing);
+
+    let wrapped = respond(&routing, "GET", "/api/wrapped").unwrap();
+    // the inner layer sees the handler's response first, the outer one runs around it
+    assert_eq!(wrapped.headers().get("X-Trace"), Some("inner outer"));
+    let plain = respond(&routing, "GET", "/api/plain").unwrap();
+    assert_eq!(plain.headers().get("X-Trace"), None);
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use single_threaded_server::{
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+};
+
+mod common;
+
+fn exchange(routing: RoutingMap, raw_request: &str) -> String {
+    common::exchange(Arc::new(routing), raw_request)
+}
+
+fn request(method: &str, path: &str) -> String {
+    format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", method, path)
+}
+
+fn items_routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::GET, || Html::new("all items".to_string()), "/items")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::POST, || Html::new("created".to_string()), "/items")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::from("PURGE"), || Html::new("purged".to_string()), "/items")
+        .unwrap();
+    routing
+        .add_any_handler(|| Html::new("anything".to_string()), "/echo")
+        .unwrap();
+    routing
+        .add_handler(HttpVerb::DELETE, || Html::new("deleted".to_string()), "/echo")
+        .unwrap();
+    routing
+}
+
+#[test]
+fn custom_methods_are_routed() {
+    let response = exchange(items_routing(), &request("PURGE", "/items"));
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("\r\n\r\npurged"));
+}
+
+#[test]
+fn any_route_answers_every_method_without_its_own_route() {
+    for method in ["GET", "PUT", "PATCH", "REPORT"] {
+        let response = exchange(items_routing(), &request(method, "/echo"));
+        assert!(response.ends_with("\r\n\r\nanything"), "{method}: {response}");
+    }
+    let response = exchange(items_routing(), &request("DELETE", "/echo"));
+    assert!(response.ends_with("\r\n\r\ndeleted"));
+}
+
+fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = format!("{}: ", name.to_lowercase());
+    response
+        .split("\r\n\r\n")
+        .next()?
+        .lines()
+        .find(|line| line.to_lowercase().starts_with(&prefix))
+        .map(|line| &line[prefix.len()..])
+}
+
+#[test]
+fn wrong_method_is_405_with_an_exact_allow_header() {
+    let response = exchange(items_routing(), &request("PUT", "/items"));
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+    assert_eq!(header(&response, "Allow"), Some("GET, HEAD, POST, OPTIONS, PURGE"));
+    let response = exchange(items_routing(), &request("PUT", "/missing"));
+    assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+}
+
+#[test]
+fn options_is_answered_from_the_routes_of_the_path() {
+    let response = exchange(items_routing(), &request("OPTIONS", "/items"));
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert_eq!(header(&response, "Allow"), Some("GET, HEAD, POST, OPTIONS, PURGE"));
+    assert_eq!(
+        header(&response, "Access-Control-Allow-Methods"),
+        Some("GET, HEAD, POST, OPTIONS, PURGE")
+    );
+}
+
+#[test]
+fn head_is_served_by_the_get_handler_without_a_body() {
+    let get = exchange(items_routing(), &request("GET", "/items"));
+    let head = exchange(items_routing(), &request("HEAD", "/items"));
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    assert_eq!(header(&head, "Content-Length"), Some("9"));
+    assert_eq!(header(&head, "Content-Length"), header(&get, "Content-Length"));
+    assert!(head.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn explicit_head_route_wins_over_get() {
+    let mut routing = items_routing();
+    routing
+        .add_handler(HttpVerb::HEAD, || Html::new("short".to_string()), "/items")
+        .unwrap();
+    let head = exchange(routing, &request("HEAD", "/items"));
+    // the GET body would have been 9 bytes
+    assert_eq!(header(&head, "Content-Length"), Some("5"));
+    assert!(head.ends_with("\r\n\r\n"));
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use single_threaded_server::{
+    cookie::{Cookie, CookieJar},
+    routing::RoutingMap,
+    secure_cookie::{Key, Keys, PrivateCookieJar, SignedCookieJar},
+};
+
+mod common;
+
+fn jar_from(cookie_header: &str) -> CookieJar {
+    let raw_request = format!("GET / HTTP/1.1\r\nHost: localhost\r\nCookie: {}\r\n\r\n", cookie_header);
+    CookieJar::from_request_headers(&common::request(&Arc::new(RoutingMap::new()), &raw_request))
+}
+
+fn key(byte: u8) -> Key {
+    Key::from_bytes(&[byte; 64]).unwrap()
+}
+
+fn keys(byte: u8) -> Arc<Keys> {
+    Arc::new(Keys::new(key(byte)))
+}
+
+// the raw value a handler's jar would put into Set-Cookie
+fn issued(delta: &[Cookie], name: &str) -> String {
+    delta
+        .iter()
+        .find(|cookie| cookie.name() == name)
+        .unwrap()
+        .value()
+        .to_string()
+}
+
+fn signed_value(keys: &Arc<Keys>, name: &str, value: &str) -> String {
+    let mut jar = SignedCookieJar::new(CookieJar::new(), Arc::clone(keys));
+    jar.add(Cookie::new(name, value));
+    issued(jar.into_jar().delta(), name)
+}
+
+fn private_value(keys: &Arc<Keys>, name: &str, value: &str) -> String {
+    let mut jar = PrivateCookieJar::new(CookieJar::new(), Arc::clone(keys));
+    jar.add(Cookie::new(name, value));
+    issued(jar.into_jar().delta(), name)
+}
+
+fn read_signed(keys: &Arc<Keys>, name: &str, value: &str) -> Option<String> {
+    let jar = SignedCookieJar::new(jar_from(&Cookie::new(name, value).encode()), Arc::clone(keys));
+    jar.get(name).map(|cookie| cookie.value().to_string())
+}
+
+fn read_private(keys: &Arc<Keys>, name: &str, value: &str) -> Option<String> {
+    let jar = PrivateCookieJar::new(jar_from(&Cookie::new(name, value).encode()), Arc::clone(keys));
+    jar.get(name).map(|cookie| cookie.value().to_string())
+}
+
+#[test]
+fn signed_and_private_cookies_round_trip() {
+    let keys = keys(1);
+    let signed = signed_value(&keys, "user", "alice; admin=no");
+    assert!(signed.ends_with(".alice; admin=no"));
+    assert_eq!(read_signed(&keys, "user", &signed).as_deref(), Some("alice; admin=no"));
+
+    let sealed = private_value(&keys, "user", "alice");
+    assert!(!sealed.contains("alice"));
+    assert_eq!(read_private(&keys, "user", &sealed).as_deref(), Some("alice"));
+    // a fresh nonce every time
+    assert_ne!(sealed, private_value(&keys, "user", "alice"));
+}
+
+#[test]
+fn tampered_cookies_are_rejected() {
+    let keys = keys(1);
+    let signed = signed_value(&keys, "user", "alice");
+    let (signature, _) = signed.split_once('.').unwrap();
+    assert_eq!(read_signed(&keys, "user", &format!("{}.admin", signature)), None);
+    let mut forged_signature = signed.clone().into_bytes();
+    forged_signature[0] = if forged_signature[0] == b'A' { b'B' } else { b'A' };
+    assert_eq!(read_signed(&keys, "user", &String::from_utf8(forged_signature).unwrap()), None);
+    assert_eq!(read_signed(&keys, "user", "alice"), None);
+
+    let sealed = private_value(&keys, "user", "alice");
+    // byte 15 of the sealed value, past the nonce and inside the ciphertext
+    let mut forged_ciphertext = sealed.into_bytes();
+    forged_ciphertext[20] = if forged_ciphertext[20] == b'A' { b'B' } else { b'A' };
+    assert_eq!(read_private(&keys, "user", &String::from_utf8(forged_ciphertext).unwrap()), None);
+    // sealed with a key the server doesn't have
+    assert_eq!(read_private(&keys, "user", &private_value(&self::keys(2), "user", "alice")), None);
+}
+
+#[test]
+fn values_are_bound_to_the_cookie_name() {
+    let keys = keys(1);
+    let signed = signed_value(&keys, "role", "admin");
+    assert_eq!(read_signed(&keys, "role", &signed).as_deref(), Some("admin"));
+    assert_eq!(read_signed(&keys, "theme", &signed), None);
+    let sealed = private_value(&keys, "role", "admin");
+    assert_eq!(read_private(&keys, "theme", &sealed), None);
+}
+
+#[test]
+fn an_equals_sign_cannot_move_between_name_and_value() {
+    let keys = keys(1);
+    let signed = signed_value(&keys, "a", "b=c");
+    assert_eq!(read_signed(&keys, "a", &signed).as_deref(), Some("b=c"));
+    let (signature, _) = signed.split_once('.').unwrap();
+    // names are percent-decoded, so a client can send a cookie called `a=b`
+    assert_eq!(read_signed(&keys, "a=b", &format!("{}.c", signature)), None);
+}
+
+#[test]
+fn previous_keys_still_read_and_cookies_are_reissued() {
+    let old_keys = keys(1);
+    let rotated_keys = Arc::new(Keys::new(key(2)).with_previous(key(1)));
+    let new_keys = keys(2);
+
+    let old_signed = signed_value(&old_keys, "user", "alice");
+    let jar = SignedCookieJar::new(jar_from(&format!("user={}", old_signed)), Arc::clone(&rotated_keys));
+    assert_eq!(jar.get("user").unwrap().value(), "alice");
+    let reissued = jar.into_jar();
+    let reissued = reissued.delta().first().unwrap();
+    assert!(reissued.encode().ends_with("; Path=/"));
+    assert_eq!(read_signed(&new_keys, "user", reissued.value()).as_deref(), Some("alice"));
+
+    let old_sealed = private_value(&old_keys, "user", "alice");
+    let jar = PrivateCookieJar::new(jar_from(&format!("user={}", old_sealed)), Arc::clone(&rotated_keys));
+    assert_eq!(jar.get("user").unwrap().value(), "alice");
+    let reissued = issued(jar.into_jar().delta(), "user");
+    assert_eq!(read_private(&new_keys, "user", &reissued).as_deref(), Some("alice"));
+
+    // cookies already under the current key are left alone
+    let current = signed_value(&new_keys, "user", "alice");
+    let jar = SignedCookieJar::new(jar_from(&format!("user={}", current)), rotated_keys);
+    assert!(jar.into_jar().delta().is_empty());
+}
+
+#[test]
+fn malformed_values_read_as_absent() {
+    let keys = keys(1);
+    let sealed = private_value(&keys, "user", "alice");
+    for value in ["", ".", "!!!.alice", "%%%", "abc", &sealed[..10], &sealed[..sealed.len() - 1]] {
+        assert_eq!(read_signed(&keys, "user", value), None, "{value}");
+        assert_eq!(read_private(&keys, "user", value), None, "{value}");
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use single_threaded_server::{
+    problem::Problem,
+    response::Html,
+    router::Router,
+    routing::RoutingMap,
+    session::{FileStore, MemoryStore, Session, SessionData, SessionLayer, SessionStore},
+};
+
+mod common;
+
+fn exchange(routing: &Arc<RoutingMap>, cookie: Option<&str>) -> String {
+    let cookie = cookie.map(|cookie| format!("Cookie: {}\r\n", cookie)).unwrap_or_default();
+    let raw_request = format!("GET /count HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\r\n", cookie);
+    common::exchange(Arc::clone(routing), &raw_request)
+}
+
+// the name=value pair a browser would send back
+fn session_cookie(response: &str) -> Option<String> {
+    response
+        .lines()
+        .find_map(|line| line.strip_prefix("Set-Cookie: "))
+        .filter(|cookie| cookie.starts_with("session_id="))
+        .map(|cookie| cookie.split(';').next().unwrap().to_string())
+}
+
+fn counting_routing(layer: SessionLayer) -> Arc<RoutingMap> {
+    let router = Router::new()
+        .get("/count", |session: Session| {
+            let count = session.get::<u32>("count").unwrap_or(0) + 1;
+            session.insert("count", count).unwrap();
+            Html::new(count.to_string())
+        })
+        .layer(layer);
+    let mut routing = RoutingMap::new();
+    routing.merge(router).unwrap();
+    Arc::new(routing)
+}
+
+fn store_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("sessions-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    directory
+}
+
+fn assert_data_persists(routing: Arc<RoutingMap>) {
+    let first = exchange(&routing, None);
+    assert!(first.ends_with("\r\n\r\n1"));
+    let cookie = session_cookie(&first).expect("a session id cookie");
+    let set_cookie = first.lines().find(|line| line.starts_with("Set-Cookie: ")).unwrap();
+    assert!(set_cookie.contains("; HttpOnly") && set_cookie.contains("; Path=/"));
+
+    let second = exchange(&routing, Some(&cookie));
+    assert!(second.ends_with("\r\n\r\n2"));
+    // the same session keeps its id
+    assert_eq!(session_cookie(&second), Some(cookie));
+    // an unknown id starts over with a new one
+    let forged = exchange(&routing, Some("session_id=unknown"));
+    assert!(forged.ends_with("\r\n\r\n1"));
+    assert_ne!(session_cookie(&forged).as_deref(), Some("session_id=unknown"));
+}
+
+#[test]
+fn memory_store_keeps_data_between_requests() {
+    assert_data_persists(counting_routing(SessionLayer::new(MemoryStore::new())));
+}
+
+#[test]
+fn file_store_keeps_data_between_requests() {
+    let directory = store_directory("persist");
+    assert_data_persists(counting_routing(SessionLayer::new(FileStore::new(&directory).unwrap())));
+    // only finished session files are left behind
+    for entry in fs::read_dir(&directory).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        assert!(name.ends_with(".json") && !name.contains(".tmp"), "{name}");
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn expired_sessions_read_as_empty() {
+    let directory = store_directory("expired");
+    let layers = [
+        SessionLayer::new(MemoryStore::new()),
+        SessionLayer::new(FileStore::new(&directory).unwrap()),
+    ];
+    for layer in layers {
+        let routing = counting_routing(layer.ttl(Duration::ZERO));
+        let cookie = session_cookie(&exchange(&routing, None)).unwrap();
+        // sent back even though its Max-Age already ran out
+        assert!(exchange(&routing, Some(&cookie)).ends_with("\r\n\r\n1"));
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn file_store_rejects_ids_outside_its_directory() {
+    let directory = store_directory("traversal");
+    let store = FileStore::new(directory.join("store")).unwrap();
+    let expires_at = SystemTime::now() + Duration::from_secs(60);
+    for id in ["../x", "..", "a/b", "a\\b", ""] {
+        let err = store.save(id, &SessionData::new(), expires_at).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{id}");
+        assert!(store.load(id).unwrap().is_none());
+        assert!(store.delete(id).is_err());
+    }
+    assert!(!directory.join("x.json").exists());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+struct UnavailableStore;
+
+impl SessionStore for UnavailableStore {
+    fn load(&self, _: &str) -> io::Result<Option<SessionData>> {
+        Err(io::Error::other("store is down"))
+    }
+    fn save(&self, _: &str, _: &SessionData, _: SystemTime) -> io::Result<()> {
+        Err(io::Error::other("store is down"))
+    }
+    fn delete(&self, _: &str) -> io::Result<()> {
+        Err(io::Error::other("store is down"))
+    }
+}
+
+#[test]
+fn store_failures_are_rejections() {
+    let routing = counting_routing(SessionLayer::new(UnavailableStore));
+    // fails on save, with no cookie there is nothing to load
+    let response = exchange(&routing, None);
+    assert!(response.starts_with("HTTP/1.1 500 Internal Server Error"));
+    assert!(response.contains("Content-Type: application/problem+json"));
+    assert!(response.contains("session is not available"));
+    assert!(!response.contains("store is down"));
+
+    let mut routing = Arc::into_inner(routing).unwrap();
+    routing.set_rejection_renderer(|problem: &Problem| {
+        Html::new(format!("sorry, {}", problem.detail.as_deref().unwrap_or_default()))
+    });
+    // fails on load this time
+    let response = exchange(&Arc::new(routing), Some("session_id=abc"));
+    assert!(response.ends_with("sorry, session is not available"));
+}
@@ -0,0 +1,72 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use single_threaded_server::{response::Html, server::Server};
+
+fn slow_server(handler_time: Duration, shutdown_timeout: Duration) -> (Server, mpsc::Receiver<()>) {
+    let mut server = Server::builder()
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .threads(2)
+        .build()
+        .unwrap();
+    server.set_shutdown_timeout(shutdown_timeout);
+    let (started, handler_started) = mpsc::channel();
+    server
+        .get("/slow", move || {
+            started.send(()).unwrap();
+            thread::sleep(handler_time);
+            Html::new("finished".to_string())
+        })
+        .unwrap();
+    (server, handler_started)
+}
+
+fn send_slow_request(server: &Server) -> TcpStream {
+    let mut client = TcpStream::connect(server.local_addrs()[0]).unwrap();
+    client
+        .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    client
+}
+
+#[test]
+fn listen_drains_in_flight_requests_after_shutdown() {
+    let (server, handler_started) = slow_server(Duration::from_millis(300), Duration::from_secs(5));
+    let shutdown = server.shutdown_handle();
+    let mut client = send_slow_request(&server);
+    let server_thread = thread::spawn(move || server.listen());
+
+    handler_started.recv_timeout(Duration::from_secs(5)).unwrap();
+    let shutdown_started = Instant::now();
+    shutdown.shutdown();
+    // the request is still answered and, with the server going away, not kept alive
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("Connection: close"));
+    assert!(response.ends_with("finished"));
+
+    server_thread.join().unwrap().unwrap();
+    assert!(shutdown_started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn listen_reports_work_still_running_at_the_deadline() {
+    let (server, handler_started) = slow_server(Duration::from_secs(3), Duration::from_millis(200));
+    let shutdown = server.shutdown_handle();
+    let _client = send_slow_request(&server);
+    let server_thread = thread::spawn(move || server.listen());
+
+    handler_started.recv_timeout(Duration::from_secs(5)).unwrap();
+    let shutdown_started = Instant::now();
+    shutdown.shutdown();
+    let err = server_thread.join().unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(shutdown_started.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,88 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use single_threaded_server::{
+    extractor::{FromRef, State, StateError},
+    response::Html,
+    server::Server,
+};
+
+struct AppState {
+    config: Config,
+    visits: AtomicUsize,
+}
+
+#[derive(Clone)]
+struct Config {
+    name: String,
+}
+
+impl FromRef<AppState> for Config {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+fn get(address: SocketAddr, path: &str) -> String {
+    let mut client = TcpStream::connect(address).unwrap();
+    write!(client, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn server() -> Server {
+    Server::builder()
+        .bind("127.0.0.1:0")
+        .unwrap()
+        .threads(2)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn handlers_extract_state_and_sub_state() {
+    let mut server = server();
+    server.with_state(AppState {
+        config: Config {
+            name: "shop".to_string(),
+        },
+        visits: AtomicUsize::new(0),
+    });
+    server.with_sub_state::<AppState, Config>().unwrap();
+    server
+        .get("/visit", |State(state): State<AppState>| {
+            let visits = state.visits.fetch_add(1, Ordering::SeqCst) + 1;
+            Html::new(format!("visit {}", visits))
+        })
+        .unwrap();
+    server
+        .get("/name", |config: State<Config>| Html::new(config.name.clone()))
+        .unwrap();
+    server
+        .get("/missing", |_: State<String>| Html::new("unreachable".to_string()))
+        .unwrap();
+    let address = server.local_addrs()[0];
+    let shutdown = server.shutdown_handle();
+    let server_thread = thread::spawn(move || server.listen());
+
+    assert!(get(address, "/visit").ends_with("visit 1"));
+    // one shared instance, not a copy per request
+    assert!(get(address, "/visit").ends_with("visit 2"));
+    assert!(get(address, "/name").ends_with("shop"));
+    assert!(get(address, "/missing").starts_with("HTTP/1.1 500 Internal Server Error"));
+
+    shutdown.shutdown();
+    server_thread.join().unwrap().unwrap();
+}
+
+#[test]
+fn sub_state_needs_its_parent_state() {
+    let mut server = server();
+    let err = server.with_sub_state::<AppState, Config>().unwrap_err();
+    assert!(matches!(err, StateError::Missing(name) if name.ends_with("AppState")));
+}
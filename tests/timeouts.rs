@@ -0,0 +1,118 @@
+use std::{
+    io::{Read, Result as IoResult, Write},
+    net::Shutdown,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use single_threaded_server::{
+    response::Html,
+    routing::{HttpVerb, RoutingMap},
+    server::ShutdownHandle,
+    stream::HttpStream,
+    task_manager::{ConnectionSettings, handle},
+};
+
+mod common;
+
+// a slowloris client, half a head up front and then one more header byte every 50ms, forever
+struct TricklingStream {
+    head: &'static [u8],
+    position: usize,
+    written: Arc<Mutex<Vec<u8>>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Read for TricklingStream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.position < self.head.len() {
+            let n = buf.len().min(self.head.len() - self.position);
+            buf[..n].copy_from_slice(&self.head[self.position..self.position + n]);
+            self.position += n;
+            return Ok(n);
+        }
+        thread::sleep(Duration::from_millis(50));
+        buf[0] = b'x';
+        Ok(1)
+    }
+}
+
+impl Write for TricklingStream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.written.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+impl HttpStream for TricklingStream {
+    fn set_read_timeout(&self, _: Option<Duration>) -> IoResult<()> {
+        Ok(())
+    }
+    fn set_write_timeout(&self, _: Option<Duration>) -> IoResult<()> {
+        Ok(())
+    }
+    fn shutdown(&self, _: Shutdown) -> IoResult<()> {
+        Ok(())
+    }
+    fn close(&mut self) -> IoResult<()> {
+        self.closed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn settings(header_deadline: Duration, body_read: Duration) -> ConnectionSettings {
+    let mut settings = ConnectionSettings::default();
+    settings.read_timeouts_mut().header_read = Duration::from_millis(200);
+    settings.read_timeouts_mut().header_deadline = header_deadline;
+    settings.read_timeouts_mut().body_read = body_read;
+    settings
+}
+
+#[test]
+fn trickled_headers_time_out_at_the_header_deadline() {
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let closed = Arc::new(AtomicBool::new(false));
+    let stream = TricklingStream {
+        head: b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Slow: ",
+        position: 0,
+        written: Arc::clone(&written),
+        closed: Arc::clone(&closed),
+    };
+    let header_deadline = Duration::from_millis(400);
+    let started = Instant::now();
+    handle(
+        stream,
+        Arc::new(RoutingMap::new()),
+        Arc::new(settings(header_deadline, Duration::from_secs(5))),
+        ShutdownHandle::default(),
+    )
+    .unwrap();
+    // every single read stays under header_read, only the deadline can end this
+    assert!(started.elapsed() < header_deadline + Duration::from_millis(300));
+    let response = String::from_utf8(written.lock().unwrap().clone()).unwrap();
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(closed.load(Ordering::SeqCst));
+}
+
+#[test]
+fn stalled_body_times_out() {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(HttpVerb::POST, |body: String| Html::new(body), "/upload")
+        .unwrap();
+    let started = Instant::now();
+    let response = common::exchange_over_tcp(
+        Arc::new(routing),
+        settings(Duration::from_secs(5), Duration::from_millis(200)),
+        b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nabc",
+    );
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "tls")]
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::Arc,
+    thread,
+};
+
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned, crypto::ring,
+    pki_types::{CertificateDer, ServerName},
+};
+use single_threaded_server::{response::Html, server::Server, tls::TlsConfig};
+
+struct SelfSigned {
+    certificate_pem: String,
+    key_pem: String,
+    certificate_der: CertificateDer<'static>,
+}
+
+fn self_signed(server_name: &str) -> SelfSigned {
+    let generated = rcgen::generate_simple_self_signed(vec![server_name.to_string()]).unwrap();
+    SelfSigned {
+        certificate_pem: generated.cert.pem(),
+        key_pem: generated.signing_key.serialize_pem(),
+        certificate_der: generated.cert.der().clone(),
+    }
+}
+
+fn https_get(port: u16, server_name: &'static str, trusted: &[&SelfSigned]) -> (String, CertificateDer<'static>) {
+    let mut roots = RootCertStore::empty();
+    for certificate in trusted {
+        roots.add(certificate.certificate_der.clone()).unwrap();
+    }
+    let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connection = ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from(server_name).unwrap(),
+    )
+    .unwrap();
+    let socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let mut stream = StreamOwned::new(connection, socket);
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let peer_certificate = stream.conn.peer_certificates().unwrap()[0].clone();
+    (String::from_utf8(response).unwrap(), peer_certificate)
+}
+
+#[test]
+fn serves_https_and_selects_certificate_by_sni() {
+    let default_certificate = self_signed("localhost");
+    let other_certificate = self_signed("other.test");
+    let tls_config = TlsConfig::from_pem(
+        default_certificate.certificate_pem.as_bytes(),
+        default_certificate.key_pem.as_bytes(),
+    )
+    .unwrap()
+    .add_sni_pem(
+        "other.test",
+        other_certificate.certificate_pem.as_bytes(),
+        other_certificate.key_pem.as_bytes(),
+    )
+    .unwrap();
+    let mut server = Server::builder()
+        .bind_tls("127.0.0.1:0", tls_config)
+        .unwrap()
+        .threads(2)
+        .build()
+        .unwrap();
+    server
+        .get("/", || Html::new("secure hello".to_string()))
+        .unwrap();
+    let port = server.local_addrs()[0].port();
+    let shutdown = server.shutdown_handle();
+    let server_thread = thread::spawn(move || server.listen().unwrap());
+
+    let trusted = [&default_certificate, &other_certificate];
+    let (response, peer_certificate) = https_get(port, "localhost", &trusted);
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("secure hello"));
+    assert_eq!(peer_certificate, default_certificate.certificate_der);
+
+    let (response, peer_certificate) = https_get(port, "other.test", &trusted);
+    assert!(response.ends_with("secure hello"));
+    assert_eq!(peer_certificate, other_certificate.certificate_der);
+
+    shutdown.shutdown();
+    server_thread.join().unwrap();
+}
@@ -0,0 +1,9 @@
+use single_threaded_server::validation::Validate;
+
+#[derive(Validate)]
+struct Signup {
+    #[validate(regex = "^[a-z+$")]
+    username: String,
+}
+
+fn main() {}
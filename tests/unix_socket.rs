@@ -0,0 +1,47 @@
+#![cfg(unix)]
+
+use std::{
+    env, fs,
+    io::{Read, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    process, thread,
+};
+
+use single_threaded_server::{response::Html, server::Server};
+
+#[test]
+fn serves_over_a_unix_socket_and_cleans_up() {
+    let path = env::temp_dir().join(format!("single_threaded_server_{}.sock", process::id()));
+    // a previous run that died leaves its socket file behind
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let mut server = Server::builder()
+        .bind_unix(&path)
+        .unix_socket_permissions(0o660)
+        .threads(1)
+        .build()
+        .unwrap();
+    server
+        .get("/", || Html::new("over unix".to_string()))
+        .unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+    let shutdown = server.shutdown_handle();
+    let server_thread = thread::spawn(move || server.listen());
+
+    let mut client = UnixStream::connect(&path).unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("over unix"));
+
+    shutdown.shutdown();
+    server_thread.join().unwrap().unwrap();
+    assert!(!path.exists());
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::Value;
+use single_threaded_server::{
+    extractor::{Json, Query},
+    response::{Html, Response, StatusCode},
+    routing::{HttpVerb, RoutingMap},
+    validation::{Valid, Validate},
+};
+
+mod common;
+
+#[derive(Deserialize, Validate)]
+struct Signup {
+    #[validate(length(min = 3, max = 12), regex = "^[a-z0-9_]+$")]
+    username: String,
+    #[validate(email)]
+    email: String,
+    #[validate(range(min = 18, message = "adults only"))]
+    age: u8,
+    #[validate(length(max = 3))]
+    nickname: Option<String>,
+    #[validate(nested)]
+    address: Address,
+    #[validate(length(min = 1), nested)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize, Validate)]
+struct Address {
+    #[validate(length(min = 2))]
+    city: String,
+}
+
+#[derive(Deserialize, Validate)]
+struct Tag {
+    #[validate(length(max = 5))]
+    name: String,
+}
+
+#[derive(Deserialize, Validate)]
+struct Page {
+    #[validate(range(min = 1, max = 50))]
+    size: u32,
+}
+
+fn routing() -> RoutingMap {
+    let mut routing = RoutingMap::new();
+    routing
+        .add_handler(
+            HttpVerb::POST,
+            |Valid(Json(signup)): Valid<Json<Signup>>| Html::new(format!("welcome {}", signup.username)),
+            "/signup",
+        )
+        .unwrap();
+    routing
+        .add_handler(
+            HttpVerb::GET,
+            |Valid(Query(page)): Valid<Query<Page>>| Html::new(format!("size {}", page.size)),
+            "/items",
+        )
+        .unwrap();
+    routing
+}
+
+fn respond(raw_request: &str) -> Response {
+    common::respond(&Arc::new(routing()), raw_request)
+}
+
+fn post_signup(body: &str) -> Response {
+    respond(&format!(
+        "POST /signup HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    ))
+}
+
+#[test]
+fn valid_input_reaches_the_handler() {
+    let response = post_signup(
+        r#"{"username": "ada_l", "email": "ada@example.com", "age": 36, "address": {"city": "London"}, "tags": [{"name": "math"}]}"#,
+    );
+    assert_eq!(response.status_code(), &StatusCode::Ok);
+    assert_eq!(response.body(), b"welcome ada_l");
+}
+
+#[test]
+fn every_violation_is_listed() {
+    let response = post_signup(
+        r#"{"username": "A!", "email": "not-an-email", "age": 12, "nickname": "toolong", "address": {"city": "X"}, "tags": [{"name": "ok"}, {"name": "far too long"}]}"#,
+    );
+    assert_eq!(response.status_code(), &StatusCode::UnprocessableEntity);
+    assert_eq!(response.headers().get("content-type"), Some("application/problem+json"));
+    let problem: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(problem["status"], 422);
+    let violations: Vec<(String, String)> = problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| {
+            (
+                error["field"].as_str().unwrap().to_string(),
+                error["rule"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    let expected = [
+        ("username", "length"),
+        ("username", "regex"),
+        ("email", "email"),
+        ("age", "range"),
+        ("nickname", "length"),
+        ("address.city", "length"),
+        ("tags[1].name", "length"),
+    ];
+    assert_eq!(
+        violations,
+        expected.map(|(field, rule)| (field.to_string(), rule.to_string()))
+    );
+    assert_eq!(problem["errors"][3]["message"], "adults only");
+    assert_eq!(problem["errors"][0]["message"], "length must be between 3 and 12");
+}
+
+#[test]
+fn validates_query_parameters() {
+    let ok = respond("GET /items?size=10 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(ok.body(), b"size 10");
+    let too_big = respond("GET /items?size=500 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(too_big.status_code(), &StatusCode::UnprocessableEntity);
+    // a query that doesn't deserialize is still the extractor's own 400
+    let malformed = respond("GET /items?size=big HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(malformed.status_code(), &StatusCode::BadRequest);
+}
+
+#[test]
+fn invalid_regex_patterns_fail_the_build() {
+    trybuild::TestCases::new().compile_fail("tests/ui/invalid_regex.rs");
+}